@@ -0,0 +1,99 @@
+use criterion::Criterion;
+use legion::query::{IntoQuery, Read, Write};
+use legion::world::World;
+use tonks::{PreparedWorld, Query, Resources, Scheduler, SchedulerBuilder};
+
+struct Position(f32);
+struct Velocity(f32);
+
+struct M0;
+struct M1;
+struct M2;
+struct M3;
+struct M4;
+struct M5;
+struct M6;
+struct M7;
+struct M8;
+struct M9;
+struct M10;
+
+/// Inserts one entity for each subset of the markers,
+/// creating an archetype for each subset.
+macro_rules! insert_archetypes {
+    ($world:ident; $($marker:ident),*;) => {
+        $world.insert((), vec![(Position(0.0), $($marker,)*)]);
+    };
+    ($world:ident; $($marker:ident),*; $head:ident $(, $tail:ident)*) => {
+        insert_archetypes!($world; $($marker),*; $($tail),*);
+        insert_archetypes!($world; $($marker,)* $head; $($tail),*);
+    };
+}
+
+/// Creates a world with 2048 archetypes, only one of which
+/// matches the benchmarked query.
+fn world() -> World {
+    let mut world = World::new();
+    insert_archetypes!(world; ; M0, M1, M2, M3, M4, M5, M6, M7, M8, M9, M10);
+    world.insert((), (0..1024).map(|i| (Position(i as f32), Velocity(1.0))));
+    world
+}
+
+#[tonks::system]
+fn iter_system(query: &mut Query<(Write<Position>, Read<Velocity>)>, world: &mut PreparedWorld) {
+    for (mut pos, vel) in query.iter(world) {
+        pos.0 += vel.0;
+    }
+}
+
+#[tonks::system]
+fn for_each_system(
+    query: &mut Query<(Write<Position>, Read<Velocity>)>,
+    world: &mut PreparedWorld,
+) {
+    query.for_each(world, |(mut pos, vel)| pos.0 += vel.0);
+}
+
+/// Benchmarks a system using the cached archetypes of a `Query`.
+fn bench_cached(c: &mut Criterion, name: &str, mut scheduler: Scheduler) {
+    let mut world = world();
+
+    c.bench_function(name, |b| {
+        b.iter(|| {
+            scheduler.execute(&mut world);
+        })
+    });
+}
+
+pub fn iter(c: &mut Criterion) {
+    let scheduler = SchedulerBuilder::new()
+        .with(iter_system)
+        .build(Resources::new());
+    bench_cached(c, "archetypes/iter/cached", scheduler);
+
+    // Legion's query evaluates all archetypes on each iteration.
+    let mut world = world();
+    let query = <(Write<Position>, Read<Velocity>)>::query();
+    c.bench_function("archetypes/iter/uncached", |b| {
+        b.iter(|| {
+            for (mut pos, vel) in query.iter(&mut world) {
+                pos.0 += vel.0;
+            }
+        })
+    });
+}
+
+pub fn for_each(c: &mut Criterion) {
+    let scheduler = SchedulerBuilder::new()
+        .with(for_each_system)
+        .build(Resources::new());
+    bench_cached(c, "archetypes/for_each/cached", scheduler);
+
+    let mut world = world();
+    let query = <(Write<Position>, Read<Velocity>)>::query();
+    c.bench_function("archetypes/for_each/uncached", |b| {
+        b.iter(|| {
+            query.for_each(&mut world, |(mut pos, vel)| pos.0 += vel.0);
+        })
+    });
+}
@@ -1,6 +1,7 @@
 #[macro_use]
 extern crate criterion;
 
+mod archetypes;
 mod no_dependencies;
 
 criterion_group!(
@@ -8,4 +9,5 @@ criterion_group!(
     no_dependencies::tonks,
     no_dependencies::shred
 );
-criterion_main!(no_dependencies);
+criterion_group!(archetypes, archetypes::iter, archetypes::for_each);
+criterion_main!(no_dependencies, archetypes);
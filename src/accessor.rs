@@ -1,30 +1,73 @@
+use crate::cache::{archetype_of, ArchetypeCache};
 use crate::system::SystemCtx;
 use crate::{MacroData, PreparedWorld, ResourceId, Resources, SystemData, SystemDataOutput};
-use legion::borrow::Ref;
+use legion::borrow::{Ref, RefMut};
 use legion::entity::Entity;
 use legion::query::{DefaultFilter, View};
 use legion::storage::{Component, ComponentTypeId};
 use legion::world::World;
 use std::marker::PhantomData;
 
-/// An entity accessor type which can be used to immutably access
-/// arbitrary components of an entity.
+/// An entity accessor type which can be used to access
+/// the components of an entity which are part of a `View`.
 pub struct EntityAccessor<'a> {
     entity: Entity,
-    _phantom: PhantomData<&'a ()>,
+    /// Components which may be accessed immutably through this accessor.
+    read_types: &'a [ComponentTypeId],
+    /// Components which may be accessed mutably through this accessor.
+    write_types: &'a [ComponentTypeId],
 }
 
 impl<'a> EntityAccessor<'a> {
     /// Retrieves a component of this entity.
+    ///
+    /// # Panics
+    /// Panics if the component is not accessed by the `View` of the `QueryAccessor`
+    /// which created this accessor.
     pub fn get_component<'b, C: Component>(&self, world: &'b PreparedWorld) -> Option<Ref<'b, C>> {
+        assert!(
+            self.read_types.contains(&ComponentTypeId::of::<C>()),
+            "component {} is not part of the accessor's view",
+            std::any::type_name::<C>()
+        );
         unsafe { &*world.world }.get_component(self.entity)
     }
+
+    /// Retrieves a component of this entity mutably.
+    ///
+    /// # Panics
+    /// Panics if the component is not written by the `View` of the `QueryAccessor`
+    /// which created this accessor.
+    pub fn get_component_mut<'b, C: Component>(
+        &self,
+        world: &'b mut PreparedWorld,
+    ) -> Option<RefMut<'b, C>> {
+        assert!(
+            self.write_types.contains(&ComponentTypeId::of::<C>()),
+            "component {} is not written by the accessor's view",
+            std::any::type_name::<C>()
+        );
+        world.get_component_mut(self.entity)
+    }
+
+    /// Returns the entity accessed by this accessor.
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
 }
 
 /// System data which allows retrieval of `EntityAccessor`s for all entities
 /// matching a `View`.
 pub struct QueryAccessor<V> {
-    _world: *const World,
+    world: *const World,
+    /// Components read or written by `V`.
+    read_types: Vec<ComponentTypeId>,
+    /// Components written by `V`.
+    write_types: Vec<ComponentTypeId>,
+    /// The default filter of `V`, which determines the archetypes within the view.
+    filter: <V as DefaultFilter>::Filter,
+    /// Archetypes matching `filter`, updated before each run.
+    archetypes: ArchetypeCache,
     _phantom: PhantomData<V>,
 }
 
@@ -36,14 +79,28 @@ where
     V: for<'v> View<'v> + DefaultFilter,
     <V as DefaultFilter>::Filter: Send + Sync,
 {
-    /// Retrieves an `EntityAccessor` for the given entity,
-    /// or `None` if the entity does not fall within this `View`.
+    /// Retrieves an `EntityAccessor` for the given entity, or `None` if
+    /// the entity is dead or does not fall within this `View`.
+    ///
+    /// An entity falls within the view if its archetype
+    /// matches the view's `DefaultFilter`.
     pub fn find(&self, entity: Entity) -> Option<EntityAccessor> {
-        // TODO: validate
-        Some(EntityAccessor {
-            entity,
-            _phantom: PhantomData,
-        })
+        let world = unsafe { &*self.world };
+        if !world.is_alive(entity) {
+            return None;
+        }
+
+        // Entities are not moved between archetypes while systems are running,
+        // so the entity's archetype is among those evaluated before this run.
+        if self.archetypes.contains(archetype_of(world, entity)?) {
+            Some(EntityAccessor {
+                entity,
+                read_types: &self.read_types,
+                write_types: &self.write_types,
+            })
+        } else {
+            None
+        }
     }
 }
 
@@ -59,8 +116,15 @@ where
         _ctx: SystemCtx,
         world: &World,
     ) -> Self {
+        let mut read_types = V::read_types();
+        read_types.extend(V::write_types());
+
         Self {
-            _world: world as *const World,
+            world: world as *const World,
+            read_types,
+            write_types: V::write_types(),
+            filter: V::filter(),
+            archetypes: ArchetypeCache::default(),
             _phantom: PhantomData,
         }
     }
@@ -74,14 +138,17 @@ where
     }
 
     fn component_reads() -> Vec<ComponentTypeId> {
-        vec![]
+        V::read_types()
     }
 
     fn component_writes() -> Vec<ComponentTypeId> {
-        vec![]
+        V::write_types()
     }
 
     fn before_execution(&'a mut self) -> Self::Output {
+        // Pick up archetypes created since the last run.
+        self.archetypes
+            .update(unsafe { &*self.world }, &self.filter);
         self
     }
 }
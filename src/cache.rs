@@ -0,0 +1,108 @@
+//! Caching of the archetypes matched by a `Query` between runs.
+
+use legion::entity::Entity;
+use legion::filter::{
+    ArchetypeFilterData, ChunkFilterData, ChunksetFilterData, EntityFilter, Filter,
+};
+use legion::storage::{ArchetypeData, ComponentStorage};
+use legion::world::{World, WorldId};
+
+/// Indices of the archetypes which match the archetype filter of a query.
+///
+/// Legion never removes archetypes, so only archetypes created since
+/// the previous update need to be evaluated. The cache is reset
+/// when the query is used with a different world.
+#[derive(Default)]
+pub(crate) struct ArchetypeCache {
+    world: Option<WorldId>,
+    /// Number of archetypes evaluated so far.
+    evaluated: usize,
+    /// Indices of the matching archetypes, in ascending order.
+    matches: Vec<usize>,
+}
+
+impl ArchetypeCache {
+    /// Evaluates the archetypes created since the last update.
+    pub(crate) fn update<F>(&mut self, world: &World, filter: &F)
+    where
+        F: EntityFilter,
+    {
+        if self.world != Some(world.id()) {
+            *self = Self {
+                world: Some(world.id()),
+                ..Self::default()
+            };
+        }
+
+        filter.init();
+
+        let storage = world.storage();
+        let (archetype_filter, _, _) = filter.filters();
+        let matches = &mut self.matches;
+        archetype_filter
+            .collect(ArchetypeFilterData {
+                component_types: storage.component_types(),
+                tag_types: storage.tag_types(),
+            })
+            .enumerate()
+            .skip(self.evaluated)
+            .filter(|(_, data)| archetype_filter.is_match(data).unwrap_or(true))
+            .for_each(|(index, _)| matches.push(index));
+
+        self.evaluated = storage.archetypes().len();
+    }
+
+    /// Returns whether the archetype with the given index matched the
+    /// archetype filter as of the last update.
+    pub(crate) fn contains(&self, archetype: usize) -> bool {
+        self.matches.binary_search(&archetype).is_ok()
+    }
+
+    /// Returns the chunks of the cached archetypes which match the chunkset
+    /// and chunk filters, along with their archetype and chunkset index.
+    ///
+    /// Unlike the archetype filter, these are evaluated on each call,
+    /// since they may depend on tag values or component versions.
+    pub(crate) fn chunks<'s, 'w, F>(
+        &'s self,
+        world: &'w World,
+        filter: &'s F,
+    ) -> impl Iterator<Item = (&'w ArchetypeData, usize, &'w ComponentStorage)> + 's
+    where
+        'w: 's,
+        F: EntityFilter,
+    {
+        let (_, chunkset_filter, chunk_filter) = filter.filters();
+        let archetypes = world.storage().archetypes();
+
+        self.matches
+            .iter()
+            .map(move |index| &archetypes[*index])
+            .flat_map(move |archetype| {
+                chunkset_filter
+                    .collect(ChunksetFilterData {
+                        archetype_data: archetype,
+                    })
+                    .zip(archetype.chunksets())
+                    .enumerate()
+                    .filter(move |(_, (data, _))| chunkset_filter.is_match(data).unwrap_or(true))
+                    .flat_map(move |(set_index, (_, chunkset))| {
+                        chunk_filter
+                            .collect(ChunkFilterData {
+                                chunks: chunkset.occupied(),
+                            })
+                            .zip(chunkset.occupied())
+                            .filter(move |(data, _)| chunk_filter.is_match(data).unwrap_or(true))
+                            .map(move |(_, chunk)| (archetype, set_index, chunk))
+                    })
+            })
+    }
+}
+
+/// Returns the index of the archetype which contains the
+/// given entity, or `None` if the entity is dead.
+pub(crate) fn archetype_of(world: &World, entity: Entity) -> Option<usize> {
+    world
+        .get_entity_location(entity)
+        .map(|location| location.archetype())
+}
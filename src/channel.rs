@@ -0,0 +1,269 @@
+//! Pull-based event consumption through double-buffered event channels.
+
+use crate::event::event_id_for;
+use crate::system::SystemCtx;
+use crate::{
+    resource_id_for, Event, EventId, MacroData, ResourceId, Resources, SystemData, SystemDataOutput,
+};
+use legion::storage::ComponentTypeId;
+use legion::world::World;
+use std::{mem, ptr};
+
+/// Double-buffered store of events of a given type, stored
+/// as a resource in the scheduler's `Resources`.
+///
+/// Events are stored in the batches in which they were triggered.
+/// Each event is assigned a consecutive index, which is used
+/// by `EventReader`s as a cursor.
+pub(crate) struct EventChannel<E> {
+    /// Batches of events triggered during the previous tick.
+    previous: Vec<Vec<E>>,
+    /// Batches of events triggered during the current tick.
+    current: Vec<Vec<E>>,
+    /// Index of the first event in `previous`.
+    previous_start: usize,
+    /// Index of the first event in `current`.
+    current_start: usize,
+    /// Index one past the last event in `current`.
+    end: usize,
+}
+
+impl<E> Default for EventChannel<E> {
+    fn default() -> Self {
+        Self {
+            previous: vec![],
+            current: vec![],
+            previous_start: 0,
+            current_start: 0,
+            end: 0,
+        }
+    }
+}
+
+impl<E> EventChannel<E> {
+    /// Moves a batch of events into this channel.
+    ///
+    /// # Safety
+    /// `events` must point to `len` initialized events. After this call,
+    /// the events are owned by the channel and must not be dropped
+    /// through `events`. They may still be read through `events`
+    /// until the next call to `swap()`.
+    unsafe fn push_raw(&mut self, events: *const E, len: usize) {
+        let mut batch = Vec::with_capacity(len);
+        ptr::copy_nonoverlapping(events, batch.as_mut_ptr(), len);
+        batch.set_len(len);
+
+        self.current.push(batch);
+        self.end += len;
+    }
+
+    /// Swaps the buffers at the end of a tick, dropping
+    /// the events from the previous tick.
+    fn swap(&mut self) {
+        self.previous = mem::replace(&mut self.current, vec![]);
+        self.previous_start = self.current_start;
+        self.current_start = self.end;
+    }
+
+    /// Returns an iterator over all stored events, starting
+    /// at the event with index `start`.
+    fn iter_from(&self, start: usize) -> impl Iterator<Item = &E> {
+        let start = start.max(self.previous_start);
+
+        self.previous
+            .iter()
+            .chain(self.current.iter())
+            .flat_map(|batch| batch.iter())
+            .skip(start - self.previous_start)
+    }
+}
+
+/// Type-erased operations on the `EventChannel` of an event type,
+/// used by the scheduler to manage channels.
+#[derive(Clone, Copy)]
+pub struct EventChannelHooks {
+    /// The ID of the event type.
+    pub(crate) event_id: EventId,
+    /// The resource ID of the `EventChannel`.
+    pub(crate) channel_id: ResourceId,
+    /// Inserts the channel into the `Resources` if it is absent.
+    pub(crate) insert: fn(&mut Resources),
+    /// Moves a batch of events into the channel. See `EventChannel::push_raw`.
+    pub(crate) push: unsafe fn(&Resources, ResourceId, *const (), usize),
+    /// Swaps the channel's buffers at the end of a tick.
+    pub(crate) swap: fn(&mut Resources, ResourceId),
+}
+
+impl EventChannelHooks {
+    /// Returns the hooks for the channel of the given event type.
+    pub fn of<E: Event>() -> Self {
+        Self {
+            event_id: event_id_for::<E>(),
+            channel_id: resource_id_for::<EventChannel<E>>(),
+            insert: insert_channel::<E>,
+            push: push_to_channel::<E>,
+            swap: swap_channel::<E>,
+        }
+    }
+}
+
+fn insert_channel<E: Event>(resources: &mut Resources) {
+    resources.insert_if_absent(EventChannel::<E>::default());
+}
+
+unsafe fn push_to_channel<E: Event>(
+    resources: &Resources,
+    channel_id: ResourceId,
+    events: *const (),
+    len: usize,
+) {
+    resources
+        .get_mut_unchecked::<EventChannel<E>>(channel_id)
+        .push_raw(events as *const E, len);
+}
+
+fn swap_channel<E: Event>(resources: &mut Resources, channel_id: ResourceId) {
+    unsafe { resources.get_mut_unchecked::<EventChannel<E>>(channel_id) }.swap();
+}
+
+/// Returns the resource ID of the `EventChannel` for the given event type.
+///
+/// `Trigger`s declare concurrent write access to this resource and
+/// `EventReader`s declare read access, so that readers never run while
+/// events they could observe are being triggered.
+///
+/// A system with both a `Trigger` and an `EventReader` for the same
+/// event type is treated as writing the channel exclusively.
+pub(crate) fn channel_id_for<E: Event>() -> ResourceId {
+    resource_id_for::<EventChannel<E>>()
+}
+
+/// System data which allows iterating over events of a given type.
+///
+/// An `EventReader` observes all events triggered during the previous
+/// tick as well as events triggered earlier during the current tick.
+/// Readers never run concurrently with systems triggering events
+/// of the same type.
+///
+/// Each reader keeps its own cursor, so each event is observed at most once
+/// per reader. Events are dropped at the end of the tick after the one in
+/// which they were triggered; a reader which does not run during that
+/// time misses them.
+pub struct EventReader<E>
+where
+    E: Event,
+{
+    channel: *const EventChannel<E>,
+    /// Index of the first event not yet observed by this reader.
+    cursor: usize,
+}
+
+// Safety: the channel pointer is valid as per the scheduler guarantees.
+unsafe impl<E: Event> Send for EventReader<E> {}
+unsafe impl<E: Event> Sync for EventReader<E> {}
+
+impl<E> EventReader<E>
+where
+    E: Event,
+{
+    /// Returns an iterator over all events which have not yet
+    /// been observed by this reader, in the order they were triggered.
+    pub fn iter(&mut self) -> impl Iterator<Item = &E> {
+        let channel = unsafe { &*self.channel };
+        let start = self.cursor;
+        self.cursor = channel.end;
+        channel.iter_from(start)
+    }
+}
+
+impl<'a, E> SystemData<'a> for EventReader<E>
+where
+    E: Event,
+{
+    type Output = &'a mut Self;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        insert_channel::<E>(resources);
+
+        Self {
+            channel: resources.get_unchecked(channel_id_for::<E>()) as *const EventChannel<E>,
+            cursor: 0,
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![channel_id_for::<E>()]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn event_channels() -> Vec<EventChannelHooks> {
+        vec![EventChannelHooks::of::<E>()]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self
+    }
+}
+
+impl<'a, E> SystemDataOutput<'a> for &'a mut EventReader<E>
+where
+    E: Event,
+{
+    type SystemData = EventReader<E>;
+}
+
+impl<E> MacroData for &'static mut EventReader<E>
+where
+    E: Event,
+{
+    type SystemData = EventReader<E>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn double_buffering() {
+        let mut channel = EventChannel::<u32>::default();
+
+        unsafe {
+            channel.push_raw([1, 2].as_ptr(), 2);
+            channel.push_raw([3].as_ptr(), 1);
+        }
+        assert_eq!(
+            channel.iter_from(0).copied().collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(
+            channel.iter_from(1).copied().collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+
+        channel.swap();
+        unsafe {
+            channel.push_raw([4].as_ptr(), 1);
+        }
+        assert_eq!(channel.iter_from(3).copied().collect::<Vec<_>>(), vec![4]);
+
+        channel.swap();
+        // Events 1, 2 and 3 have been dropped.
+        assert_eq!(channel.iter_from(0).copied().collect::<Vec<_>>(), vec![4]);
+        assert_eq!(channel.iter_from(4).count(), 0);
+    }
+}
@@ -0,0 +1,509 @@
+//! Event types defined at runtime, e.g. by a scripting layer.
+//!
+//! Dynamic event types are identified by a name rather than a Rust type.
+//! Their events are either plain bytes with a layout given when the type
+//! is allocated, or boxed `dyn Any` payloads.
+
+use crate::event::{
+    drop_events_in_place, dynamic_event_id_for, event_kind, event_layout, event_name, not_stopped,
+    EventKind, HandleStrategy,
+};
+use crate::resources::Resource;
+use crate::scheduler::TaskMessage;
+use crate::system::{SystemCtx, SystemDataOutput, SYSTEM_ID_MAPPINGS};
+use crate::{
+    resource_id_for, EventId, MacroData, RawEventHandler, ResourceId, Resources, SystemData,
+    SystemId,
+};
+use bumpalo::Bump;
+use legion::storage::ComponentTypeId;
+use legion::world::World;
+use std::alloc::Layout;
+use std::any::Any;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Payload of an event of a boxed dynamic event type. See `boxed_event_id`.
+pub type BoxedEvent = Box<dyn Any + Send + Sync>;
+
+/// Returns the event ID for the dynamic event type with the given name,
+/// allocating it if the name has not been used before.
+///
+/// Events of this type are plain bytes with the given layout. They are
+/// never dropped, so they must not own any resources.
+///
+/// Like the layout of a Rust type, the size of the layout must be a
+/// multiple of its alignment, since events are stored contiguously.
+///
+/// # Panics
+/// Panics if the size of the layout is not a multiple of its alignment,
+/// or if the name was previously used with a different layout.
+pub fn dynamic_event_id(name: &str, layout: Layout) -> EventId {
+    assert_eq!(
+        layout,
+        layout.pad_to_align(),
+        "layout of dynamic event {} is not padded to its alignment",
+        name
+    );
+    dynamic_event_id_for(name, layout, EventKind::Bytes, drop_nothing)
+}
+
+/// Returns the event ID for the boxed dynamic event type with the given
+/// name, allocating it if the name has not been used before.
+///
+/// Events of this type are `BoxedEvent`s, which are dropped once handled.
+///
+/// # Panics
+/// Panics if the name was previously used for a dynamic event type of bytes.
+pub fn boxed_event_id(name: &str) -> EventId {
+    dynamic_event_id_for(
+        name,
+        Layout::new::<BoxedEvent>(),
+        EventKind::Boxed,
+        drop_events_in_place::<BoxedEvent>,
+    )
+}
+
+unsafe fn drop_nothing(_ptr: *const (), _len: usize) {}
+
+/// Checks that the given bytes are a valid event of the
+/// dynamic event type with the given ID, returning its layout.
+///
+/// # Panics
+/// Panics if the event type is not a dynamic event type of bytes,
+/// or if the length of `bytes` does not match its layout.
+fn validate_bytes(id: EventId, bytes: &[u8]) -> Layout {
+    assert_eq!(
+        event_kind(id),
+        EventKind::Bytes,
+        "event {} is not a dynamic event of bytes",
+        event_name(id)
+    );
+    let layout = event_layout(id);
+    assert_eq!(
+        bytes.len(),
+        layout.size(),
+        "size of event {} does not match its layout",
+        event_name(id)
+    );
+    layout
+}
+
+/// Checks that the given event type is a boxed dynamic event type.
+///
+/// # Panics
+/// Panics if the event type is not a boxed dynamic event type.
+fn validate_boxed(id: EventId) {
+    assert_eq!(
+        event_kind(id),
+        EventKind::Boxed,
+        "event {} is not a boxed dynamic event",
+        event_name(id)
+    );
+}
+
+/// Copies an event of a dynamic event type of bytes into the
+/// bump allocator, returning a pointer to the event. See `validate_bytes`.
+pub(crate) fn alloc_bytes(bump: &Bump, id: EventId, bytes: &[u8]) -> *const () {
+    let layout = validate_bytes(id, bytes);
+
+    let ptr = bump.alloc_layout(layout).as_ptr();
+    // Safety: the allocation has the size of the bytes.
+    unsafe {
+        ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len());
+    }
+    ptr as *const ()
+}
+
+/// Moves an event of a boxed dynamic event type into the bump
+/// allocator, returning a pointer to the event. See `validate_boxed`.
+pub(crate) fn alloc_boxed(bump: &Bump, id: EventId, payload: BoxedEvent) -> *const () {
+    validate_boxed(id);
+    bump.alloc(payload) as *mut BoxedEvent as *const ()
+}
+
+/// System data which allows you to trigger events of dynamic event types.
+///
+/// Unlike `Trigger`, dynamic events cannot be read by `EventReader`s.
+pub struct RawTrigger {
+    ctx: SystemCtx,
+    bytes: Vec<(EventId, Vec<u8>)>,
+    boxed: Vec<(EventId, BoxedEvent)>,
+}
+
+impl RawTrigger {
+    fn new(ctx: SystemCtx) -> Self {
+        Self {
+            ctx,
+            bytes: vec![],
+            boxed: vec![],
+        }
+    }
+
+    /// Triggers an event of a dynamic event type of bytes. See `dynamic_event_id`.
+    ///
+    /// # Panics
+    /// Panics if the event type is not a dynamic event type of bytes,
+    /// or if the length of `bytes` does not match its layout.
+    pub fn trigger_bytes(&mut self, id: EventId, bytes: &[u8]) {
+        validate_bytes(id, bytes);
+        self.bytes.push((id, bytes.to_vec()));
+    }
+
+    /// Triggers an event of a boxed dynamic event type. See `boxed_event_id`.
+    ///
+    /// # Panics
+    /// Panics if the event type is not a boxed dynamic event type.
+    pub fn trigger_boxed(&mut self, id: EventId, payload: BoxedEvent) {
+        validate_boxed(id);
+        self.boxed.push((id, payload));
+    }
+
+    /// Sends all triggered events to the scheduler.
+    fn flush(&mut self) {
+        let bump = self.ctx.bump.get();
+
+        let bytes = self
+            .bytes
+            .drain(..)
+            .map(|(id, bytes)| (id, alloc_bytes(bump, id, &bytes)));
+        let boxed = self
+            .boxed
+            .drain(..)
+            .map(|(id, payload)| (id, alloc_boxed(bump, id, payload)));
+
+        for (id, ptr) in bytes.chain(boxed) {
+            self.ctx
+                .sender
+                .send(TaskMessage::TriggerEvents {
+                    id,
+                    source: self.ctx.id,
+                    ptr,
+                    len: 1,
+                })
+                .unwrap();
+        }
+    }
+}
+
+impl<'a> SystemData<'a> for RawTrigger {
+    type Output = &'a mut Self;
+
+    unsafe fn load_from_resources(
+        _resources: &mut Resources,
+        ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        Self::new(ctx)
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self
+    }
+
+    fn after_execution(&mut self) {
+        self.flush();
+    }
+}
+
+impl<'a> SystemDataOutput<'a> for &'a mut RawTrigger {
+    type SystemData = RawTrigger;
+}
+
+impl MacroData for &'static mut RawTrigger {
+    type SystemData = RawTrigger;
+}
+
+/// A batch of events of a dynamic event type passed to a `DynamicEventHandler`.
+pub struct DynamicEvents<'a> {
+    ptr: *const u8,
+    len: usize,
+    kind: EventKind,
+    layout: Layout,
+    stopped: &'a [AtomicBool],
+    stops_propagation: bool,
+}
+
+impl<'a> DynamicEvents<'a> {
+    /// Returns an iterator over the events which have
+    /// not been stopped by a handler with a higher priority.
+    pub fn iter(&self) -> impl Iterator<Item = DynamicEvent<'a>> {
+        let (ptr, kind, layout, stopped, stops_propagation) = (
+            self.ptr,
+            self.kind,
+            self.layout,
+            self.stopped,
+            self.stops_propagation,
+        );
+
+        (0..self.len)
+            .filter(move |index| !stopped[*index].load(Ordering::Relaxed))
+            .map(move |index| DynamicEvent {
+                // Safety: the index is within the batch.
+                ptr: unsafe { ptr.add(index * layout.size()) },
+                kind,
+                layout,
+                stopped: &stopped[index],
+                stops_propagation,
+            })
+    }
+}
+
+/// An event of a dynamic event type.
+pub struct DynamicEvent<'a> {
+    ptr: *const u8,
+    kind: EventKind,
+    layout: Layout,
+    stopped: &'a AtomicBool,
+    stops_propagation: bool,
+}
+
+impl<'a> DynamicEvent<'a> {
+    /// Returns the bytes of this event.
+    ///
+    /// # Panics
+    /// Panics if the event is not of a dynamic event type of bytes.
+    pub fn bytes(&self) -> &'a [u8] {
+        assert_eq!(self.kind, EventKind::Bytes, "event is not made of bytes");
+        // Safety: events of bytes were initialized from a byte slice.
+        unsafe { std::slice::from_raw_parts(self.ptr, self.layout.size()) }
+    }
+
+    /// Returns the payload of this event.
+    ///
+    /// # Panics
+    /// Panics if the event is not of a boxed dynamic event type.
+    pub fn payload(&self) -> &'a (dyn Any + Send + Sync) {
+        assert_eq!(self.kind, EventKind::Boxed, "event is not boxed");
+        // Safety: the event type stores `BoxedEvent`s.
+        unsafe { &**(self.ptr as *const BoxedEvent) }
+    }
+
+    /// Stops this event from being passed on to handlers with a lower priority.
+    ///
+    /// # Panics
+    /// Panics if the handler was not created with `with_stops_propagation`.
+    pub fn stop_propagation(&self) {
+        assert!(
+            self.stops_propagation,
+            "handler may not stop the propagation of events"
+        );
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Access to the resources declared by a `DynamicEventHandler`.
+pub struct DynamicResources<'a> {
+    resources: &'a Resources,
+    reads: &'a [ResourceId],
+    writes: &'a [ResourceId],
+}
+
+impl<'a> DynamicResources<'a> {
+    /// Returns a reference to the resource.
+    ///
+    /// # Panics
+    /// Panics if the handler did not declare read or write access
+    /// to the resource, or if the resource does not exist.
+    pub fn get<T: Resource>(&self) -> &T {
+        let id = resource_id_for::<T>();
+        assert!(
+            self.reads.contains(&id) || self.writes.contains(&id),
+            "resource {} was not declared by the handler",
+            std::any::type_name::<T>()
+        );
+        // Safety: the scheduler ensures no conflicting access exists.
+        unsafe { self.resources.get_unchecked(id) }
+    }
+
+    /// Returns a mutable reference to the resource.
+    ///
+    /// # Panics
+    /// Panics if the handler did not declare write access
+    /// to the resource, or if the resource does not exist.
+    pub fn get_mut<T: Resource>(&mut self) -> &mut T {
+        let id = resource_id_for::<T>();
+        assert!(
+            self.writes.contains(&id),
+            "resource {} was not declared as written by the handler",
+            std::any::type_name::<T>()
+        );
+        // Safety: the scheduler ensures no conflicting access exists,
+        // and `&mut self` prevents aliasing within the handler.
+        unsafe {
+            let resource = self.resources.get_mut_unchecked(id);
+            self.resources.change_tracker(id).mark_changed();
+            resource
+        }
+    }
+}
+
+type DynHandlerFn =
+    dyn FnMut(&DynamicEvents, &mut DynamicResources, &mut RawTrigger) + Send + Sync + 'static;
+
+/// An event handler for a dynamic event type, implemented by a closure.
+///
+/// The closure is passed the events, the resources declared
+/// through `with_read` and `with_write`, and a `RawTrigger`.
+pub struct DynamicEventHandler {
+    id: SystemId,
+    name: &'static str,
+    event_id: EventId,
+    priority: i32,
+    stops_propagation: bool,
+    resource_reads: Vec<ResourceId>,
+    resource_writes: Vec<ResourceId>,
+    handler: Box<DynHandlerFn>,
+    /// Trigger passed to the handler, or `None` if it has not yet been initialized.
+    trigger: Option<RawTrigger>,
+}
+
+impl DynamicEventHandler {
+    /// Creates a new `DynamicEventHandler` handling events with the given ID.
+    ///
+    /// # Panics
+    /// Panics if the event type is not a dynamic event type.
+    pub fn new<F>(event_id: EventId, name: &'static str, handler: F) -> Self
+    where
+        F: FnMut(&DynamicEvents, &mut DynamicResources, &mut RawTrigger) + Send + Sync + 'static,
+    {
+        assert_ne!(
+            event_kind(event_id),
+            EventKind::Typed,
+            "event {} is not a dynamic event",
+            event_name(event_id)
+        );
+
+        Self {
+            id: SYSTEM_ID_MAPPINGS.lock().alloc(),
+            name,
+            event_id,
+            priority: 0,
+            stops_propagation: false,
+            resource_reads: vec![],
+            resource_writes: vec![],
+            handler: Box::new(handler),
+            trigger: None,
+        }
+    }
+
+    /// Sets the priority of this handler. See `RawEventHandler::priority`.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Allows this handler to stop the propagation of events
+    /// using `DynamicEvent::stop_propagation`.
+    pub fn with_stops_propagation(mut self) -> Self {
+        self.stops_propagation = true;
+        self
+    }
+
+    /// Declares read access to the given resource.
+    pub fn with_read<T: Resource>(mut self) -> Self {
+        self.resource_reads.push(resource_id_for::<T>());
+        self
+    }
+
+    /// Declares write access to the given resource.
+    pub fn with_write<T: Resource>(mut self) -> Self {
+        self.resource_writes.push(resource_id_for::<T>());
+        self
+    }
+}
+
+unsafe impl RawEventHandler for DynamicEventHandler {
+    fn id(&self) -> SystemId {
+        self.id
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn event_id(&self) -> EventId {
+        self.event_id
+    }
+
+    fn strategy(&self) -> HandleStrategy {
+        HandleStrategy::EndOfTick
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn stops_propagation(&self) -> bool {
+        self.stops_propagation
+    }
+
+    fn resource_reads(&self) -> &[ResourceId] {
+        &self.resource_reads
+    }
+
+    fn resource_writes(&self) -> &[ResourceId] {
+        &self.resource_writes
+    }
+
+    fn init(&mut self, _resources: &mut Resources, ctx: SystemCtx, _world: &World) {
+        self.trigger = Some(RawTrigger::new(ctx));
+    }
+
+    unsafe fn handle_raw_batch(
+        &mut self,
+        events: *const (),
+        events_len: usize,
+        resources: &Resources,
+        ctx: SystemCtx,
+        world: &World,
+    ) {
+        let stopped = not_stopped(events_len);
+        self.handle_raw_batch_with_stopped(events, events_len, &stopped, resources, ctx, world);
+    }
+
+    unsafe fn handle_raw_batch_with_stopped(
+        &mut self,
+        events: *const (),
+        events_len: usize,
+        stopped: &[AtomicBool],
+        resources: &Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) {
+        let events = DynamicEvents {
+            ptr: events as *const u8,
+            len: events_len,
+            kind: event_kind(self.event_id),
+            layout: event_layout(self.event_id),
+            stopped,
+            stops_propagation: self.stops_propagation,
+        };
+        let mut resources = DynamicResources {
+            resources,
+            reads: &self.resource_reads,
+            writes: &self.resource_writes,
+        };
+        let trigger = self.trigger.as_mut().unwrap();
+
+        (self.handler)(&events, &mut resources, trigger);
+
+        trigger.flush();
+    }
+}
@@ -1,8 +1,10 @@
+use crate::channel::{channel_id_for, EventChannelHooks};
 use crate::mappings::Mappings;
-use crate::scheduler::TaskMessage;
-use crate::system::{SystemCtx, SystemDataOutput, SYSTEM_ID_MAPPINGS};
+use crate::scheduler::{OrExtend, TaskMessage};
+use crate::system::{merge_concurrent_reads, SystemCtx, SystemDataOutput, SYSTEM_ID_MAPPINGS};
 use crate::{resource_id_for_component, MacroData, ResourceId, Resources, SystemData, SystemId};
-use hashbrown::HashSet;
+use bumpalo::Bump;
+use hashbrown::{HashMap, HashSet};
 use lazy_static::lazy_static;
 use legion::storage::ComponentTypeId;
 use legion::world::World;
@@ -10,6 +12,8 @@ use parking_lot::Mutex;
 use std::alloc::Layout;
 use std::any::TypeId;
 use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
 /// ID of an event type, allocated consecutively.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
@@ -24,6 +28,33 @@ impl From<usize> for EventId {
 lazy_static! {
     pub static ref EVENT_ID_MAPPINGS: Mutex<Mappings<TypeId, EventId>> =
         Mutex::new(Mappings::new());
+    /// Type information of events, used to handle type-erased events.
+    ///
+    /// This vector is indexed by the `EventId`.
+    static ref EVENT_INFO: Mutex<Vec<EventInfo>> = Mutex::new(vec![]);
+    /// IDs of dynamic event types, keyed by their names.
+    static ref DYNAMIC_EVENT_IDS: Mutex<HashMap<String, EventId>> = Mutex::new(HashMap::new());
+}
+
+/// How events of a type are represented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum EventKind {
+    /// The events are values of a Rust type implementing `Event`.
+    Typed,
+    /// The events are plain bytes with a layout given at runtime.
+    Bytes,
+    /// The events are `BoxedEvent`s.
+    Boxed,
+}
+
+/// Type information of an event type.
+#[derive(Debug, Clone, Copy)]
+struct EventInfo {
+    name: &'static str,
+    layout: Layout,
+    kind: EventKind,
+    /// Drops a slice of events of this type in place.
+    drop: unsafe fn(*const (), usize),
 }
 
 /// Returns the event ID for the given type.
@@ -31,7 +62,90 @@ pub fn event_id_for<E>() -> EventId
 where
     E: Event,
 {
-    EVENT_ID_MAPPINGS.lock().get_or_alloc(TypeId::of::<E>())
+    let mut mappings = EVENT_ID_MAPPINGS.lock();
+    let id = mappings.get_or_alloc(TypeId::of::<E>());
+
+    // IDs are allocated consecutively, so a new ID is
+    // always the next index into the vector of type information.
+    let mut info = EVENT_INFO.lock();
+    if info.len() == id.0 {
+        info.push(EventInfo {
+            name: std::any::type_name::<E>(),
+            layout: Layout::new::<E>(),
+            kind: EventKind::Typed,
+            drop: drop_events_in_place::<E>,
+        });
+    }
+
+    id
+}
+
+/// Returns the event ID for the dynamic event type with the given
+/// name, allocating it if the name has not been used before.
+///
+/// # Panics
+/// Panics if the name was previously used with a different layout or kind.
+pub(crate) fn dynamic_event_id_for(
+    name: &str,
+    layout: Layout,
+    kind: EventKind,
+    drop: unsafe fn(*const (), usize),
+) -> EventId {
+    let mut ids = DYNAMIC_EVENT_IDS.lock();
+    if let Some(id) = ids.get(name) {
+        let info = EVENT_INFO.lock()[id.0];
+        assert!(
+            info.layout == layout && info.kind == kind,
+            "dynamic event {} was allocated twice with different layouts",
+            name
+        );
+        return *id;
+    }
+
+    let mut mappings = EVENT_ID_MAPPINGS.lock();
+    let id = mappings.alloc();
+
+    let mut info = EVENT_INFO.lock();
+    debug_assert_eq!(info.len(), id.0);
+    info.push(EventInfo {
+        // Event types are never deallocated, so leaking the name is fine.
+        name: Box::leak(name.to_owned().into_boxed_str()),
+        layout,
+        kind,
+        drop,
+    });
+
+    ids.insert(name.to_owned(), id);
+    id
+}
+
+/// Returns the type name of the event with the given ID.
+pub(crate) fn event_name(id: EventId) -> &'static str {
+    EVENT_INFO.lock()[id.0].name
+}
+
+/// Returns the memory layout of the event with the given ID.
+pub(crate) fn event_layout(id: EventId) -> Layout {
+    EVENT_INFO.lock()[id.0].layout
+}
+
+/// Returns how events with the given ID are represented.
+pub(crate) fn event_kind(id: EventId) -> EventKind {
+    EVENT_INFO.lock()[id.0].kind
+}
+
+/// Drops a slice of events of the type with the given ID in place.
+///
+/// # Safety
+/// `ptr` must point to `len` initialized events of the type with the
+/// given ID, which must not be used again after this call.
+pub(crate) unsafe fn drop_events(id: EventId, ptr: *const (), len: usize) {
+    let drop = EVENT_INFO.lock()[id.0].drop;
+    drop(ptr, len);
+}
+
+pub(crate) unsafe fn drop_events_in_place<E>(ptr: *const (), len: usize) {
+    ptr::drop_in_place(std::slice::from_raw_parts_mut(ptr as *mut E, len));
 }
 
 /// Marker trait for types which can be triggered as events.
@@ -62,6 +176,125 @@ impl Default for HandleStrategy {
     }
 }
 
+/// Policy for events which are triggered but have
+/// no event handlers or `EventReader`s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnhandledEventPolicy {
+    /// Unhandled events are silently dropped.
+    ///
+    /// This is the default policy.
+    Ignore,
+    /// A warning naming the event type is logged for each unhandled batch of events.
+    /// Without the `log` feature, unhandled events are ignored instead.
+    Log,
+    /// Unhandled events are counted in the `UnhandledEvents` resource,
+    /// which is updated at the end of each dispatch.
+    Collect,
+    /// The scheduler panics at the end of the dispatch in which an event
+    /// is unhandled, once all systems and event handlers have completed.
+    /// In release builds, unhandled events are ignored instead.
+    Panic,
+}
+
+impl Default for UnhandledEventPolicy {
+    fn default() -> Self {
+        UnhandledEventPolicy::Ignore
+    }
+}
+
+/// Resource counting events which were triggered but had no event handlers
+/// or `EventReader`s. Only used with `UnhandledEventPolicy::Collect`.
+///
+/// Counts accumulate across dispatches until `clear()` is called.
+#[derive(Debug, Default)]
+pub struct UnhandledEvents {
+    /// Number of unhandled events of each type.
+    ///
+    /// This vector is indexed by the `EventId`.
+    counts: Vec<usize>,
+}
+
+impl UnhandledEvents {
+    /// Returns the number of unhandled events of the given type.
+    pub fn count<E: Event>(&self) -> usize {
+        self.counts.get(event_id_for::<E>().0).copied().unwrap_or(0)
+    }
+
+    /// Returns whether no events were unhandled.
+    pub fn is_empty(&self) -> bool {
+        self.counts.iter().all(|count| *count == 0)
+    }
+
+    /// Returns an iterator over the type names and counts
+    /// of all event types which were unhandled.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(id, count)| (event_name(EventId(id)), *count))
+    }
+
+    /// Resets all counts to zero.
+    pub fn clear(&mut self) {
+        self.counts.clear();
+    }
+
+    pub(crate) fn record(&mut self, id: EventId, count: usize) {
+        *self.counts.get_mut_or_extend(id.0) += count;
+    }
+}
+
+/// Time at which scheduled events are to be handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum ScheduledTime {
+    /// The events are handled during the dispatch with the given tick.
+    Tick(u64),
+    /// The events are handled during the first dispatch
+    /// which starts once the given instant has passed.
+    Instant(Instant),
+}
+
+/// A type-erased batch of events which is scheduled
+/// to be handled during a later dispatch.
+pub(crate) trait ScheduledBatch: Send + Sync {
+    /// Moves the events into the bump allocator, returning
+    /// a pointer to the slice of events and its length.
+    fn move_into(self: Box<Self>, bump: &Bump) -> (*const (), usize);
+}
+
+impl<E> ScheduledBatch for Vec<E>
+where
+    E: Event,
+{
+    fn move_into(mut self: Box<Self>, bump: &Bump) -> (*const (), usize) {
+        let len = self.len();
+        let ptr: *mut E = bump
+            .alloc_layout(Layout::for_value(self.as_slice()))
+            .cast::<E>()
+            .as_ptr();
+
+        // Safety: the events are moved bitwise, and the vector
+        // no longer considers them initialized afterwards.
+        unsafe {
+            ptr::copy_nonoverlapping(self.as_ptr(), ptr, len);
+            self.set_len(0);
+        }
+
+        (ptr as *const (), len)
+    }
+}
+
+/// Whether an event should be passed on to handlers
+/// with a lower priority after being handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Propagation {
+    /// The event is passed on to the next handler.
+    Continue,
+    /// The event is not passed on to any further handlers.
+    Stop,
+}
+
 /// A raw event handler.
 ///
 /// # Safety
@@ -81,10 +314,40 @@ pub unsafe trait RawEventHandler: Send + Sync + 'static {
     /// Returns the strategy that should be used to invoke this handler.
     fn strategy(&self) -> HandleStrategy;
 
+    /// Returns the priority of this handler. Handlers of the same event
+    /// with a higher priority are run first.
+    ///
+    /// The default implementation of this function returns 0.
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    /// Returns whether this handler may stop the propagation of events.
+    /// Handlers which return `false` must not modify the `stopped` flags
+    /// passed to `handle_raw_batch_with_stopped`.
+    ///
+    /// The default implementation of this function returns `false`.
+    fn stops_propagation(&self) -> bool {
+        false
+    }
+
     /// Returns the resources read by this event handler.
     fn resource_reads(&self) -> &[ResourceId];
     /// Returns the resources written by this event handler.
     fn resource_writes(&self) -> &[ResourceId];
+    /// Returns the internally synchronized resources which this event handler
+    /// accesses concurrently. See `Concurrent`.
+    ///
+    /// The default implementation of this function returns an empty slice.
+    fn resource_concurrent_writes(&self) -> &[ResourceId] {
+        &[]
+    }
+    /// Returns the event channels read by this event handler. See `EventReader`.
+    ///
+    /// The default implementation of this function returns an empty slice.
+    fn event_channels(&self) -> &[EventChannelHooks] {
+        &[]
+    }
 
     fn init(&mut self, resources: &mut Resources, ctx: SystemCtx, world: &World);
 
@@ -101,6 +364,57 @@ pub unsafe trait RawEventHandler: Send + Sync + 'static {
         ctx: SystemCtx,
         world: &World,
     );
+
+    /// Handles a slice of events, skipping events whose propagation was stopped.
+    /// This is the function called by the scheduler.
+    ///
+    /// `stopped` has one flag for each event. Events whose flag is set
+    /// have been stopped by a handler with a higher priority and must not be handled.
+    /// The handler sets the flags of events for which it stops propagation.
+    ///
+    /// The default implementation calls `handle_raw_batch` for each run of
+    /// consecutive events which were not stopped. Handlers which return `true`
+    /// from `stops_propagation` must override this function.
+    ///
+    /// # Safety
+    /// Same as for `handle_raw_batch`.
+    unsafe fn handle_raw_batch_with_stopped(
+        &mut self,
+        events: *const (),
+        events_len: usize,
+        stopped: &[AtomicBool],
+        resources: &Resources,
+        ctx: SystemCtx,
+        world: &World,
+    ) {
+        let size = event_layout(self.event_id()).size();
+        let is_stopped = |index: usize| stopped[index].load(Ordering::Relaxed);
+
+        let mut start = 0;
+        while start < events_len {
+            if is_stopped(start) {
+                start += 1;
+                continue;
+            }
+
+            let end = (start..events_len)
+                .find(|index| is_stopped(*index))
+                .unwrap_or(events_len);
+            self.handle_raw_batch(
+                (events as *const u8).add(start * size) as *const (),
+                end - start,
+                resources,
+                ctx.clone(),
+                world,
+            );
+            start = end;
+        }
+    }
+}
+
+/// Returns propagation flags for a batch of events none of which were stopped.
+pub(crate) fn not_stopped(len: usize) -> Vec<AtomicBool> {
+    (0..len).map(|_| AtomicBool::new(false)).collect()
 }
 
 // High-level event handlers.
@@ -125,6 +439,40 @@ pub trait EventHandler<E: Event>: Send + Sync + 'static {
             .for_each(|event| self.handle(event, &mut data));
     }
 
+    /// Handles a single event, returning whether it should be
+    /// passed on to handlers with a lower priority.
+    ///
+    /// This function is only called if `stops_propagation()` returns `true`,
+    /// in which case it is called instead of `handle_batch`.
+    ///
+    /// The default implementation for this function calls `handle`
+    /// and returns `Propagation::Continue`.
+    fn handle_propagating(
+        &mut self,
+        event: &E,
+        data: &mut <Self::HandlerData as SystemData>::Output,
+    ) -> Propagation {
+        self.handle(event, data);
+        Propagation::Continue
+    }
+
+    /// Returns whether this handler may stop the propagation of events
+    /// using `handle_propagating`.
+    ///
+    /// The default implementation of this function returns `false`.
+    fn stops_propagation(&self) -> bool {
+        false
+    }
+
+    /// Returns the priority of this handler. Handlers of the same event
+    /// with a higher priority are run first; handlers with equal priorities
+    /// are run in the order they were added.
+    ///
+    /// The default implementation of this function returns 0.
+    fn priority(&self) -> i32 {
+        0
+    }
+
     /// Returns the strategy that should be used to invoke this handler.
     /// The default implementation of this function returns `HandleStrategy::default()`.
     fn strategy(&self) -> HandleStrategy {
@@ -146,6 +494,10 @@ where
     resource_reads: Vec<ResourceId>,
     /// Cached resource writes.
     resource_writes: Vec<ResourceId>,
+    /// Cached concurrent resource writes.
+    resource_concurrent_writes: Vec<ResourceId>,
+    /// Cached event channels.
+    event_channels: Vec<EventChannelHooks>,
     /// Cached component reads.
     component_reads: Vec<ComponentTypeId>,
     /// Cached component writes.
@@ -162,6 +514,8 @@ where
 {
     /// Creates a new `CachedEventHandler` caching the given event handler.
     pub fn new(inner: H, name: &'static str) -> Self {
+        H::HandlerData::validate();
+
         let component_writes = H::HandlerData::component_writes()
             .into_iter()
             .collect::<HashSet<_>>();
@@ -181,11 +535,20 @@ where
                 .map(|comp| resource_id_for_component(comp)),
         );
 
+        let mut resource_concurrent_writes = H::HandlerData::resource_concurrent_writes();
+        merge_concurrent_reads(
+            &mut resource_reads,
+            &mut resource_writes,
+            &mut resource_concurrent_writes,
+        );
+
         Self {
             id: SYSTEM_ID_MAPPINGS.lock().alloc(),
             event_id: event_id_for::<E>(),
             resource_reads,
             resource_writes,
+            resource_concurrent_writes,
+            event_channels: H::HandlerData::event_channels(),
             component_reads: H::HandlerData::component_reads(),
             component_writes: H::HandlerData::component_writes(),
             data: None,
@@ -216,6 +579,14 @@ where
         self.inner.strategy()
     }
 
+    fn priority(&self) -> i32 {
+        self.inner.priority()
+    }
+
+    fn stops_propagation(&self) -> bool {
+        self.inner.stops_propagation()
+    }
+
     fn resource_reads(&self) -> &[ResourceId] {
         &self.resource_reads
     }
@@ -224,6 +595,14 @@ where
         &self.resource_writes
     }
 
+    fn resource_concurrent_writes(&self) -> &[ResourceId] {
+        &self.resource_concurrent_writes
+    }
+
+    fn event_channels(&self) -> &[EventChannelHooks] {
+        &self.event_channels
+    }
+
     fn init(&mut self, resources: &mut Resources, ctx: SystemCtx, world: &World) {
         let mut data = unsafe { H::HandlerData::load_from_resources(resources, ctx, world) };
         data.init(resources, &self.component_reads, &self.component_writes);
@@ -234,6 +613,19 @@ where
         &mut self,
         events: *const (),
         events_len: usize,
+        resources: &Resources,
+        ctx: SystemCtx,
+        world: &World,
+    ) {
+        let stopped = not_stopped(events_len);
+        self.handle_raw_batch_with_stopped(events, events_len, &stopped, resources, ctx, world);
+    }
+
+    unsafe fn handle_raw_batch_with_stopped(
+        &mut self,
+        events: *const (),
+        events_len: usize,
+        stopped: &[AtomicBool],
         _resources: &Resources,
         _ctx: SystemCtx,
         _world: &World,
@@ -245,8 +637,26 @@ where
         let events = std::slice::from_raw_parts(events as *const E, events_len);
 
         let data = self.data.as_mut().unwrap();
-
-        self.inner.handle_batch(events, data.before_execution());
+        let mut output = data.before_execution();
+
+        let is_stopped = |stopped: &AtomicBool| stopped.load(Ordering::Relaxed);
+
+        if self.inner.stops_propagation() {
+            for (event, stopped) in events.iter().zip(stopped) {
+                if !is_stopped(stopped)
+                    && self.inner.handle_propagating(event, &mut output) == Propagation::Stop
+                {
+                    stopped.store(true, Ordering::Relaxed);
+                }
+            }
+        } else if stopped.iter().any(is_stopped) {
+            // Some events were stopped, so they can't be handled as one batch.
+            for (event, _) in events.iter().zip(stopped).filter(|(_, s)| !is_stopped(*s)) {
+                self.inner.handle(event, &mut output);
+            }
+        } else {
+            self.inner.handle_batch(events, output);
+        }
 
         data.after_execution();
     }
@@ -259,6 +669,9 @@ where
 {
     ctx: SystemCtx,
     queued: Vec<E>,
+    /// Batches of events to be handled during a later
+    /// dispatch, with one batch for each due time.
+    scheduled: Vec<(ScheduledTime, Vec<E>)>,
     id: EventId,
 }
 
@@ -276,6 +689,7 @@ where
         Self {
             ctx,
             queued: vec![],
+            scheduled: vec![],
             id: event_id_for::<E>(),
         }
     }
@@ -288,6 +702,12 @@ where
         vec![]
     }
 
+    fn resource_concurrent_writes() -> Vec<ResourceId> {
+        // The scheduler moves triggered events into the channel read
+        // by `EventReader`s, so triggers must not run concurrently with readers.
+        vec![channel_id_for::<E>()]
+    }
+
     fn component_reads() -> Vec<ComponentTypeId> {
         vec![]
     }
@@ -303,6 +723,18 @@ where
     fn after_execution(&mut self) {
         // TODO: end-of-system handlers
 
+        for (time, events) in self.scheduled.drain(..) {
+            self.ctx
+                .sender
+                .send(TaskMessage::ScheduleEvents {
+                    id: self.id,
+                    source: self.ctx.id,
+                    time,
+                    events: Box::new(events),
+                })
+                .unwrap();
+        }
+
         // Move events to bump-allocated slice and send to scheduler.
         let len = self.queued.len();
 
@@ -313,7 +745,7 @@ where
         let ptr: *mut E = self
             .ctx
             .bump
-            .get_or_default()
+            .get()
             .alloc_layout(Layout::for_value(self.queued.as_slice()))
             .cast::<E>()
             .as_ptr();
@@ -329,6 +761,7 @@ where
             .sender
             .send(TaskMessage::TriggerEvents {
                 id: self.id,
+                source: self.ctx.id,
                 ptr: ptr as *const (),
                 len,
             })
@@ -347,6 +780,43 @@ where
     pub fn trigger_batched(&mut self, events: impl IntoIterator<Item = E>) {
         self.queued.extend(events);
     }
+
+    /// Triggers an event which will be handled during the
+    /// dispatch `ticks` ticks after the current one.
+    ///
+    /// A delay of 0 is equivalent to `trigger`.
+    pub fn trigger_after(&mut self, event: E, ticks: u64) {
+        let tick = self.current_tick() + ticks;
+        self.trigger_at(event, tick);
+    }
+
+    /// Triggers an event which will be handled during the dispatch
+    /// with the given tick. See `Scheduler::tick`.
+    ///
+    /// If the tick is not after the current one, the
+    /// event is handled during the current dispatch.
+    pub fn trigger_at(&mut self, event: E, tick: u64) {
+        self.schedule(ScheduledTime::Tick(tick), event);
+    }
+
+    /// Triggers an event which will be handled during the first
+    /// dispatch which starts once the given delay has elapsed.
+    pub fn trigger_in(&mut self, event: E, delay: Duration) {
+        self.schedule(ScheduledTime::Instant(Instant::now() + delay), event);
+    }
+
+    /// Adds an event to the batch of scheduled events with the given due time.
+    fn schedule(&mut self, time: ScheduledTime, event: E) {
+        match self.scheduled.iter_mut().find(|(at, _)| *at == time) {
+            Some((_, events)) => events.push(event),
+            None => self.scheduled.push((time, vec![event])),
+        }
+    }
+
+    /// Returns the tick of the current dispatch. See `Scheduler::tick`.
+    pub fn current_tick(&self) -> u64 {
+        self.ctx.tick.load(Ordering::Acquire)
+    }
 }
 
 impl<'a, E> SystemDataOutput<'a> for &'a mut Trigger<E>
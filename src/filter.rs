@@ -0,0 +1,600 @@
+//! Filters which restrict the entities yielded by a `Query`.
+
+use hashbrown::{HashMap, HashSet};
+use legion::entity::Entity;
+use legion::filter::filter_fns::{component, tag};
+use legion::filter::{
+    ChunkFilterData, ChunksetFilterData, ComponentFilter, EntityFilter, EntityFilterTuple, Filter,
+    Passthrough, TagFilter,
+};
+use legion::storage::{Component, ComponentStorage, ComponentTypeId, Tag, TagTypeId};
+use parking_lot::Mutex;
+use std::any::{Any, TypeId};
+use std::marker::PhantomData;
+use std::ops::{BitAnd, Not};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// A filter which can be added to a `Query` in addition
+/// to the default filter of its `View`.
+///
+/// Filters can be combined with tuples, e.g.
+/// `Query<Read<Position>, (Without<Static>, Changed<Position>)>`.
+pub trait QueryFilter: 'static {
+    /// The Legion filter which is combined with the default filter of the view.
+    type Filter: EntityFilter + Send + Sync;
+
+    /// Creates the Legion filter. Change-detection filters keep a handle
+    /// to `ticks`, which is owned by the `Query` and advanced each time
+    /// its system runs.
+    fn filter(ticks: &Arc<QueryTicks>) -> Self::Filter;
+
+    /// Returns the components read by this filter.
+    fn component_reads() -> Vec<ComponentTypeId>;
+
+    /// Returns the components and tags which all matched entities have.
+    ///
+    /// The default implementation of this function returns an empty vector.
+    fn required() -> Vec<ArchetypeElement> {
+        vec![]
+    }
+
+    /// Returns the components and tags which no matched entity has.
+    ///
+    /// The default implementation of this function returns an empty vector.
+    fn excluded() -> Vec<ArchetypeElement> {
+        vec![]
+    }
+
+    /// Returns the components whose modifications this filter detects.
+    ///
+    /// The default implementation of this function returns an empty vector.
+    fn changed() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+}
+
+/// A component or tag type which is part of an archetype.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArchetypeElement {
+    Component(ComponentTypeId),
+    Tag(TagTypeId),
+}
+
+/// Component accesses restricted to the entities of some archetypes.
+///
+/// Used by the scheduler to detect that two accesses to the
+/// same component can never touch the same entities.
+/// See `SchedulerBuilder::set_archetype_conflicts`.
+#[derive(Debug, Clone, Default)]
+pub struct ArchetypeAccess {
+    /// Components which are read.
+    pub reads: Vec<ComponentTypeId>,
+    /// Components which are written.
+    pub writes: Vec<ComponentTypeId>,
+    /// Components and tags which all accessed entities have.
+    pub required: Vec<ArchetypeElement>,
+    /// Components and tags which no accessed entity has.
+    pub excluded: Vec<ArchetypeElement>,
+}
+
+impl ArchetypeAccess {
+    /// Creates an access to the given components on all archetypes.
+    pub fn new(reads: Vec<ComponentTypeId>, writes: Vec<ComponentTypeId>) -> Self {
+        Self {
+            reads,
+            writes,
+            required: vec![],
+            excluded: vec![],
+        }
+    }
+
+    /// Returns whether this access applies to all archetypes.
+    pub fn is_unrestricted(&self) -> bool {
+        self.required.is_empty() && self.excluded.is_empty()
+    }
+
+    /// Returns whether no entity can be accessed through both `self` and `other`.
+    pub fn is_disjoint(&self, other: &ArchetypeAccess) -> bool {
+        self.required
+            .iter()
+            .any(|element| other.excluded.contains(element))
+            || other
+                .required
+                .iter()
+                .any(|element| self.excluded.contains(element))
+    }
+
+    /// Returns whether `self` and `other` may access the same
+    /// component of the same entity, with at least one of them writing.
+    pub fn conflicts_with(&self, other: &ArchetypeAccess) -> bool {
+        let overlaps =
+            self.writes.iter().any(|component| {
+                other.reads.contains(component) || other.writes.contains(component)
+            }) || self
+                .reads
+                .iter()
+                .any(|component| other.writes.contains(component));
+
+        overlaps && !self.is_disjoint(other)
+    }
+}
+
+impl QueryFilter for () {
+    type Filter = EntityFilterTuple<Passthrough, Passthrough, Passthrough>;
+
+    fn filter(_ticks: &Arc<QueryTicks>) -> Self::Filter {
+        EntityFilterTuple::new(Passthrough, Passthrough, Passthrough)
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+}
+
+impl<A, B> QueryFilter for (A, B)
+where
+    A: QueryFilter,
+    B: QueryFilter,
+    A::Filter: BitAnd<B::Filter>,
+    <A::Filter as BitAnd<B::Filter>>::Output: EntityFilter + Send + Sync,
+{
+    type Filter = <A::Filter as BitAnd<B::Filter>>::Output;
+
+    fn filter(ticks: &Arc<QueryTicks>) -> Self::Filter {
+        A::filter(ticks) & B::filter(ticks)
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        let mut reads = A::component_reads();
+        reads.extend(B::component_reads());
+        reads
+    }
+
+    fn required() -> Vec<ArchetypeElement> {
+        let mut required = A::required();
+        required.extend(B::required());
+        required
+    }
+
+    fn excluded() -> Vec<ArchetypeElement> {
+        let mut excluded = A::excluded();
+        excluded.extend(B::excluded());
+        excluded
+    }
+
+    fn changed() -> Vec<ComponentTypeId> {
+        let mut changed = A::changed();
+        changed.extend(B::changed());
+        changed
+    }
+}
+
+/// Implements `QueryFilter` for larger tuples by nesting
+/// them into pairs, e.g. `(A, B, C)` as `(A, (B, C))`.
+macro_rules! impl_filter_tuple {
+    ($head:ident, $($tail:ident),+) => {
+        impl<$head, $($tail),+> QueryFilter for ($head, $($tail),+)
+        where
+            ($head, ($($tail),+)): QueryFilter,
+        {
+            type Filter = <($head, ($($tail),+)) as QueryFilter>::Filter;
+
+            fn filter(ticks: &Arc<QueryTicks>) -> Self::Filter {
+                <($head, ($($tail),+))>::filter(ticks)
+            }
+
+            fn component_reads() -> Vec<ComponentTypeId> {
+                <($head, ($($tail),+))>::component_reads()
+            }
+
+            fn required() -> Vec<ArchetypeElement> {
+                <($head, ($($tail),+))>::required()
+            }
+
+            fn excluded() -> Vec<ArchetypeElement> {
+                <($head, ($($tail),+))>::excluded()
+            }
+
+            fn changed() -> Vec<ComponentTypeId> {
+                <($head, ($($tail),+))>::changed()
+            }
+        }
+    };
+}
+
+impl_filter_tuple!(A, B, C);
+impl_filter_tuple!(A, B, C, D);
+impl_filter_tuple!(A, B, C, D, E);
+impl_filter_tuple!(A, B, C, D, E, F);
+
+/// Filter which only matches entities which have a component of type `T`,
+/// without accessing it.
+pub struct With<T>(PhantomData<T>);
+
+impl<T> QueryFilter for With<T>
+where
+    T: Component,
+{
+    type Filter = EntityFilterTuple<ComponentFilter<T>, Passthrough, Passthrough>;
+
+    fn filter(_ticks: &Arc<QueryTicks>) -> Self::Filter {
+        component::<T>()
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        // Only the archetype is inspected.
+        vec![]
+    }
+
+    fn required() -> Vec<ArchetypeElement> {
+        vec![ArchetypeElement::Component(ComponentTypeId::of::<T>())]
+    }
+}
+
+/// Filter which only matches entities which do not have a component of type `T`.
+pub struct Without<T>(PhantomData<T>);
+
+impl<T> QueryFilter for Without<T>
+where
+    T: Component,
+{
+    type Filter = <EntityFilterTuple<ComponentFilter<T>, Passthrough, Passthrough> as Not>::Output;
+
+    fn filter(_ticks: &Arc<QueryTicks>) -> Self::Filter {
+        !component::<T>()
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn excluded() -> Vec<ArchetypeElement> {
+        vec![ArchetypeElement::Component(ComponentTypeId::of::<T>())]
+    }
+}
+
+/// Filter which only matches entities which have a tag of type `T`.
+///
+/// Tags are shared between all entities in a chunk and
+/// cannot be modified while systems run, so no access is declared.
+pub struct Tagged<T>(PhantomData<T>);
+
+impl<T> QueryFilter for Tagged<T>
+where
+    T: Tag,
+{
+    type Filter = EntityFilterTuple<TagFilter<T>, Passthrough, Passthrough>;
+
+    fn filter(_ticks: &Arc<QueryTicks>) -> Self::Filter {
+        tag::<T>()
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn required() -> Vec<ArchetypeElement> {
+        vec![ArchetypeElement::Tag(TagTypeId::of::<T>())]
+    }
+}
+
+/// Filter which only matches entities which do not have a tag of type `T`.
+pub struct Untagged<T>(PhantomData<T>);
+
+impl<T> QueryFilter for Untagged<T>
+where
+    T: Tag,
+{
+    type Filter = <EntityFilterTuple<TagFilter<T>, Passthrough, Passthrough> as Not>::Output;
+
+    fn filter(_ticks: &Arc<QueryTicks>) -> Self::Filter {
+        !tag::<T>()
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn excluded() -> Vec<ArchetypeElement> {
+        vec![ArchetypeElement::Tag(TagTypeId::of::<T>())]
+    }
+}
+
+/// Filter which only matches entities whose tag of type `T` is equal
+/// to the value set through `Query::set_tag_value`.
+///
+/// Until a value has been set, no entities match.
+pub struct TagValue<T>(PhantomData<T>);
+
+impl<T> QueryFilter for TagValue<T>
+where
+    T: Tag,
+{
+    type Filter = EntityFilterTuple<TagFilter<T>, TagValueChunksets<T>, Passthrough>;
+
+    fn filter(ticks: &Arc<QueryTicks>) -> Self::Filter {
+        EntityFilterTuple::new(
+            tag::<T>().arch_filter,
+            TagValueChunksets {
+                ticks: Arc::clone(ticks),
+                _phantom: PhantomData,
+            },
+            Passthrough,
+        )
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn required() -> Vec<ArchetypeElement> {
+        vec![ArchetypeElement::Tag(TagTypeId::of::<T>())]
+    }
+}
+
+/// Chunkset filter used by `TagValue`.
+pub struct TagValueChunksets<T> {
+    ticks: Arc<QueryTicks>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> Clone for TagValueChunksets<T> {
+    fn clone(&self) -> Self {
+        Self {
+            ticks: Arc::clone(&self.ticks),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Filter<ChunksetFilterData<'a>> for TagValueChunksets<T>
+where
+    T: Tag,
+{
+    type Iter = std::slice::Iter<'a, T>;
+
+    fn init(&self) {}
+
+    fn collect(&self, source: ChunksetFilterData<'a>) -> Self::Iter {
+        // Safety: the archetype filter only matches archetypes with a tag of type `T`.
+        unsafe {
+            source
+                .archetype_data
+                .tags()
+                .get(TagTypeId::of::<T>())
+                .unwrap()
+                .data_slice::<T>()
+                .iter()
+        }
+    }
+
+    fn is_match(&self, tag: &<Self::Iter as Iterator>::Item) -> Option<bool> {
+        Some(self.ticks.tag_value_matches(*tag))
+    }
+}
+
+/// State of a `Query` which is shared with its filters:
+/// change-detection ticks and values matched by `TagValue`.
+#[derive(Default)]
+pub struct QueryTicks {
+    /// Highest component version observed before the current run.
+    last_run: AtomicU64,
+    /// Highest component version observed so far.
+    high_water_mark: AtomicU64,
+    /// Entities observed with each component filtered by `Added`.
+    known_entities: Mutex<HashMap<ComponentTypeId, HashSet<Entity>>>,
+    /// Values matched by `TagValue` filters, keyed by the type of the tag.
+    tag_values: Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>>,
+}
+
+impl QueryTicks {
+    /// Called before each run of the system owning the query.
+    pub(crate) fn advance(&self) {
+        self.last_run.store(
+            self.high_water_mark.load(Ordering::Acquire),
+            Ordering::Release,
+        );
+    }
+
+    /// Sets the value matched by `TagValue<T>` filters.
+    pub(crate) fn set_tag_value<T: Tag>(&self, value: T) {
+        self.tag_values
+            .lock()
+            .insert(TypeId::of::<T>(), Box::new(value));
+    }
+
+    /// Returns whether the given tag is equal to the value set for its type.
+    fn tag_value_matches<T: Tag>(&self, tag: &T) -> bool {
+        self.tag_values
+            .lock()
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+            .map_or(false, |value| value == tag)
+    }
+
+    /// Returns whether the given component version is newer
+    /// than the previous run.
+    fn changed(&self, version: u64) -> bool {
+        self.high_water_mark.fetch_max(version, Ordering::AcqRel);
+        version > self.last_run.load(Ordering::Acquire)
+    }
+
+    /// Returns whether a chunk whose `component` has the given version
+    /// contains entities which have not been observed with the component.
+    fn entities_added(
+        &self,
+        component: ComponentTypeId,
+        version: u64,
+        entities: &[Entity],
+    ) -> bool {
+        // Inserting entities into a chunk bumps the version of its components,
+        // so only chunks which changed since the previous run are inspected.
+        if !self.changed(version) {
+            return false;
+        }
+
+        let mut known = self.known_entities.lock();
+        let known = known.entry(component).or_insert_with(HashSet::new);
+        entities
+            .iter()
+            .fold(false, |added, entity| known.insert(*entity) || added)
+    }
+}
+
+/// Filter which only matches chunks in which components of type `T`
+/// were modified since the system last ran.
+///
+/// Change detection uses Legion's chunk versioning, so it operates
+/// on whole chunks: if one entity in a chunk changes, all entities
+/// in that chunk are yielded. Mutably borrowing a chunk through
+/// a query counts as a change.
+///
+/// A query which writes `T` cannot be filtered by `Changed<T>`, since it
+/// would observe its own writes on each run. Creating a system with
+/// such a query panics.
+///
+/// On the first run, all chunks containing `T` match.
+pub struct Changed<T>(PhantomData<T>);
+
+impl<T> QueryFilter for Changed<T>
+where
+    T: Component,
+{
+    type Filter = EntityFilterTuple<Passthrough, Passthrough, ChangedChunks<T>>;
+
+    fn filter(ticks: &Arc<QueryTicks>) -> Self::Filter {
+        EntityFilterTuple::new(
+            Passthrough,
+            Passthrough,
+            ChangedChunks {
+                ticks: Arc::clone(ticks),
+                _phantom: PhantomData,
+            },
+        )
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![ComponentTypeId::of::<T>()]
+    }
+
+    fn required() -> Vec<ArchetypeElement> {
+        vec![ArchetypeElement::Component(ComponentTypeId::of::<T>())]
+    }
+
+    fn changed() -> Vec<ComponentTypeId> {
+        vec![ComponentTypeId::of::<T>()]
+    }
+}
+
+/// Chunk filter used by `Changed`.
+pub struct ChangedChunks<T> {
+    ticks: Arc<QueryTicks>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> Clone for ChangedChunks<T> {
+    fn clone(&self) -> Self {
+        Self {
+            ticks: Arc::clone(&self.ticks),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Filter<ChunkFilterData<'a>> for ChangedChunks<T>
+where
+    T: Component,
+{
+    type Iter = std::slice::Iter<'a, ComponentStorage>;
+
+    fn init(&self) {}
+
+    fn collect(&self, source: ChunkFilterData<'a>) -> Self::Iter {
+        source.chunks.iter()
+    }
+
+    fn is_match(&self, chunk: &<Self::Iter as Iterator>::Item) -> Option<bool> {
+        Some(match chunk.components(ComponentTypeId::of::<T>()) {
+            Some(components) => self.ticks.changed(components.version()),
+            None => false,
+        })
+    }
+}
+
+/// Filter which only matches chunks into which entities with
+/// a component of type `T` were inserted since the system last ran.
+///
+/// Like `Changed`, this operates on whole chunks. A chunk matches
+/// when it contains an entity which the query has not observed with `T`
+/// before. Entities which move to another chunk because they gained or
+/// lost an unrelated component are not reported again, and neither are
+/// entities which lose `T` and later regain it.
+///
+/// On the first run, all non-empty chunks containing `T` match.
+pub struct Added<T>(PhantomData<T>);
+
+impl<T> QueryFilter for Added<T>
+where
+    T: Component,
+{
+    type Filter = EntityFilterTuple<Passthrough, Passthrough, AddedChunks<T>>;
+
+    fn filter(ticks: &Arc<QueryTicks>) -> Self::Filter {
+        EntityFilterTuple::new(
+            Passthrough,
+            Passthrough,
+            AddedChunks {
+                ticks: Arc::clone(ticks),
+                _phantom: PhantomData,
+            },
+        )
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![ComponentTypeId::of::<T>()]
+    }
+
+    fn required() -> Vec<ArchetypeElement> {
+        vec![ArchetypeElement::Component(ComponentTypeId::of::<T>())]
+    }
+}
+
+/// Chunk filter used by `Added`.
+pub struct AddedChunks<T> {
+    ticks: Arc<QueryTicks>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> Clone for AddedChunks<T> {
+    fn clone(&self) -> Self {
+        Self {
+            ticks: Arc::clone(&self.ticks),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Filter<ChunkFilterData<'a>> for AddedChunks<T>
+where
+    T: Component,
+{
+    type Iter = std::slice::Iter<'a, ComponentStorage>;
+
+    fn init(&self) {}
+
+    fn collect(&self, source: ChunkFilterData<'a>) -> Self::Iter {
+        source.chunks.iter()
+    }
+
+    fn is_match(&self, chunk: &<Self::Iter as Iterator>::Item) -> Option<bool> {
+        let component = ComponentTypeId::of::<T>();
+        Some(match chunk.components(component) {
+            Some(components) => {
+                self.ticks
+                    .entities_added(component, components.version(), chunk.entities())
+            }
+            None => false,
+        })
+    }
+}
@@ -0,0 +1,421 @@
+//! Recording of triggered events for debugging and replay.
+//!
+//! Like resources, events are serialized under a stable, user-provided
+//! type name rather than their `EventId`, since IDs depend on the order
+//! in which types are first encountered and thus differ between runs.
+
+use crate::event::{event_id_for, ScheduledBatch};
+use crate::{Event, EventId};
+use hashbrown::HashMap;
+use serde::de::{
+    DeserializeOwned, DeserializeSeed, Error as _, IgnoredAny, MapAccess, SeqAccess, Visitor,
+};
+use serde::ser::{SerializeSeq, SerializeStruct};
+use serde::{Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// A type-erased batch of recorded events.
+trait RecordedBatch: Send + Sync {
+    /// Returns the events as an erased `Serialize`.
+    fn as_serialize(&self) -> &dyn erased_serde::Serialize;
+
+    /// Converts the batch into one which can be scheduled for replay.
+    fn into_scheduled(self: Box<Self>) -> Box<dyn ScheduledBatch>;
+}
+
+impl<E> RecordedBatch for Vec<E>
+where
+    E: Event + Serialize,
+{
+    fn as_serialize(&self) -> &dyn erased_serde::Serialize {
+        self
+    }
+
+    fn into_scheduled(self: Box<Self>) -> Box<dyn ScheduledBatch> {
+        self
+    }
+}
+
+/// A serializable event type registered in an `EventRegistry`.
+struct Registration {
+    /// Stable name of the event type.
+    name: &'static str,
+    /// ID of the event type.
+    id: EventId,
+    /// Clones a slice of events into a recorded batch.
+    record: unsafe fn(*const (), usize) -> Box<dyn RecordedBatch>,
+    /// Deserializes a recorded batch.
+    deserialize: for<'de> fn(
+        &mut dyn erased_serde::Deserializer<'de>,
+    ) -> Result<Box<dyn RecordedBatch>, erased_serde::Error>,
+}
+
+/// Registry of event types which are recorded in an `EventJournal`.
+///
+/// Only events whose types have been registered are recorded;
+/// all other events are skipped.
+#[derive(Default)]
+pub struct EventRegistry {
+    registrations: Vec<Registration>,
+    /// Mappings from type names to indices into `registrations`.
+    by_name: HashMap<&'static str, usize>,
+    /// Mappings from event IDs to indices into `registrations`.
+    by_id: HashMap<EventId, usize>,
+}
+
+impl EventRegistry {
+    /// Creates an empty `EventRegistry`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a serializable event type under the given name.
+    ///
+    /// Events are cloned when they are recorded, since they
+    /// are still passed to event handlers afterwards.
+    ///
+    /// # Panics
+    /// Panics if the type or the name has already been registered.
+    pub fn register<E>(&mut self, name: &'static str)
+    where
+        E: Event + Clone + Serialize + DeserializeOwned,
+    {
+        let id = event_id_for::<E>();
+        assert!(
+            !self.by_id.contains_key(&id),
+            "event type {} registered twice",
+            std::any::type_name::<E>()
+        );
+        assert!(
+            !self.by_name.contains_key(name),
+            "event name {} registered twice",
+            name
+        );
+
+        self.by_name.insert(name, self.registrations.len());
+        self.by_id.insert(id, self.registrations.len());
+        self.registrations.push(Registration {
+            name,
+            id,
+            record: record_events::<E>,
+            deserialize: deserialize_events::<E>,
+        });
+    }
+
+    /// Registers a serializable event type, returning the
+    /// `EventRegistry` for method chaining.
+    pub fn with<E>(mut self, name: &'static str) -> Self
+    where
+        E: Event + Clone + Serialize + DeserializeOwned,
+    {
+        self.register::<E>(name);
+        self
+    }
+
+    /// Records a batch of events, returning `None` if
+    /// the event type has not been registered.
+    ///
+    /// # Safety
+    /// `ptr` must point to `len` initialized events
+    /// of the type with the given ID.
+    pub(crate) unsafe fn record(
+        &self,
+        id: EventId,
+        tick: u64,
+        source: Option<&str>,
+        ptr: *const (),
+        len: usize,
+    ) -> Option<JournalEntry> {
+        let registration = &self.registrations[*self.by_id.get(&id)?];
+        Some(JournalEntry {
+            name: registration.name,
+            id,
+            tick,
+            source: source.map(String::from),
+            events: (registration.record)(ptr, len),
+        })
+    }
+
+    /// Deserializes a journal previously serialized with `serde`.
+    ///
+    /// Returns an error if the data contains an event name
+    /// which has not been registered.
+    pub fn deserialize_journal<'de, D>(&self, deserializer: D) -> Result<EventJournal, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(JournalVisitor { registry: self })
+    }
+}
+
+/// A recorded batch of events.
+pub struct JournalEntry {
+    name: &'static str,
+    id: EventId,
+    tick: u64,
+    /// Name of the system or event handler which triggered the
+    /// events. Unlike its `SystemId`, this is stable across runs.
+    source: Option<String>,
+    events: Box<dyn RecordedBatch>,
+}
+
+impl JournalEntry {
+    /// Returns the registered name of the event type.
+    pub fn event_name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the tick during which the events were triggered. See `Scheduler::tick`.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Returns the name of the system or event handler which triggered
+    /// the events, or `None` if they were triggered through the `Scheduler`.
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_ref().map(String::as_str)
+    }
+
+    pub(crate) fn into_scheduled(self) -> (EventId, u64, Option<String>, Box<dyn ScheduledBatch>) {
+        (
+            self.id,
+            self.tick,
+            self.source,
+            self.events.into_scheduled(),
+        )
+    }
+}
+
+impl Serialize for JournalEntry {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut entry = serializer.serialize_struct("JournalEntry", 4)?;
+        entry.serialize_field("event", self.name)?;
+        entry.serialize_field("tick", &self.tick)?;
+        entry.serialize_field("source", &self.source)?;
+        entry.serialize_field("events", self.events.as_serialize())?;
+        entry.end()
+    }
+}
+
+/// Journal of event batches triggered in a `Scheduler`, in the order
+/// they were triggered. See `EventsBuilder::set_event_journal`.
+///
+/// A journal serializes as a sequence of entries, each containing the
+/// registered name of the event type, the tick, the name of the triggering
+/// system, and the events. It can be loaded with
+/// `EventRegistry::deserialize_journal` and passed to `Scheduler::replay`.
+#[derive(Default)]
+pub struct EventJournal {
+    entries: Vec<JournalEntry>,
+}
+
+impl EventJournal {
+    /// Creates an empty `EventJournal`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns an iterator over the recorded batches of events.
+    pub fn entries(&self) -> impl Iterator<Item = &JournalEntry> + '_ {
+        self.entries.iter()
+    }
+
+    /// Returns the number of recorded batches of events.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether no events were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Retains only the entries for which the predicate returns `true`.
+    ///
+    /// This may be used to only replay events triggered
+    /// through the `Scheduler`, since events triggered by
+    /// systems are triggered again when the systems run.
+    pub fn retain(&mut self, f: impl FnMut(&JournalEntry) -> bool) {
+        self.entries.retain(f);
+    }
+
+    pub(crate) fn push(&mut self, entry: JournalEntry) {
+        self.entries.push(entry);
+    }
+
+    pub(crate) fn into_entries(self) -> impl Iterator<Item = JournalEntry> {
+        self.entries.into_iter()
+    }
+}
+
+impl Serialize for EventJournal {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.entries.len()))?;
+        for entry in &self.entries {
+            seq.serialize_element(entry)?;
+        }
+        seq.end()
+    }
+}
+
+struct JournalVisitor<'a> {
+    registry: &'a EventRegistry,
+}
+
+impl<'a, 'de> Visitor<'de> for JournalVisitor<'a> {
+    type Value = EventJournal;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of journal entries")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut journal = EventJournal::new();
+        while let Some(entry) = seq.next_element_seed(EntrySeed {
+            registry: self.registry,
+        })? {
+            journal.push(entry);
+        }
+        Ok(journal)
+    }
+}
+
+/// Deserializes a single journal entry.
+#[derive(Clone, Copy)]
+struct EntrySeed<'a> {
+    registry: &'a EventRegistry,
+}
+
+impl<'a> EntrySeed<'a> {
+    fn registration<E: serde::de::Error>(&self, name: &str) -> Result<&'a Registration, E> {
+        let index = *self
+            .registry
+            .by_name
+            .get(name)
+            .ok_or_else(|| E::custom(format!("unknown event {}", name)))?;
+        Ok(&self.registry.registrations[index])
+    }
+}
+
+const ENTRY_FIELDS: &[&str] = &["event", "tick", "source", "events"];
+
+impl<'a, 'de> DeserializeSeed<'de> for EntrySeed<'a> {
+    type Value = JournalEntry;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("JournalEntry", ENTRY_FIELDS, self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for EntrySeed<'a> {
+    type Value = JournalEntry;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a journal entry")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let missing = |index| A::Error::invalid_length(index, &"a journal entry");
+
+        let name: String = seq.next_element()?.ok_or_else(|| missing(0))?;
+        let registration = self.registration(&name)?;
+        let tick: u64 = seq.next_element()?.ok_or_else(|| missing(1))?;
+        let source: Option<String> = seq.next_element()?.ok_or_else(|| missing(2))?;
+        let events = seq
+            .next_element_seed(EventsSeed { registration })?
+            .ok_or_else(|| missing(3))?;
+
+        Ok(JournalEntry {
+            name: registration.name,
+            id: registration.id,
+            tick,
+            source,
+            events,
+        })
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut registration = None;
+        let mut tick = None;
+        let mut source = None;
+        let mut events = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "event" => registration = Some(self.registration(&map.next_value::<String>()?)?),
+                "tick" => tick = Some(map.next_value::<u64>()?),
+                "source" => source = Some(map.next_value::<Option<String>>()?),
+                "events" => {
+                    // The events can only be deserialized once their type is known.
+                    let registration = registration
+                        .ok_or_else(|| A::Error::custom("`events` must follow `event`"))?;
+                    events = Some(map.next_value_seed(EventsSeed { registration })?);
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        let registration = registration.ok_or_else(|| A::Error::missing_field("event"))?;
+        Ok(JournalEntry {
+            name: registration.name,
+            id: registration.id,
+            tick: tick.ok_or_else(|| A::Error::missing_field("tick"))?,
+            source: source.ok_or_else(|| A::Error::missing_field("source"))?,
+            events: events.ok_or_else(|| A::Error::missing_field("events"))?,
+        })
+    }
+}
+
+/// Deserializes the events of a journal entry.
+struct EventsSeed<'a> {
+    registration: &'a Registration,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for EventsSeed<'a> {
+    type Value = Box<dyn RecordedBatch>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut erased = <dyn erased_serde::Deserializer<'de>>::erase(deserializer);
+        (self.registration.deserialize)(&mut erased).map_err(D::Error::custom)
+    }
+}
+
+unsafe fn record_events<E>(ptr: *const (), len: usize) -> Box<dyn RecordedBatch>
+where
+    E: Event + Clone + Serialize,
+{
+    Box::new(std::slice::from_raw_parts(ptr as *const E, len).to_vec())
+}
+
+fn deserialize_events<'de, E>(
+    deserializer: &mut dyn erased_serde::Deserializer<'de>,
+) -> Result<Box<dyn RecordedBatch>, erased_serde::Error>
+where
+    E: Event + Serialize + DeserializeOwned,
+{
+    let events: Vec<E> = erased_serde::deserialize(deserializer)?;
+    Ok(Box::new(events))
+}
@@ -13,26 +13,58 @@ pub extern crate inventory;
 pub extern crate parking_lot;
 
 mod accessor;
+mod cache;
+mod channel;
+mod dynamic;
 mod event;
+mod filter;
+#[cfg(feature = "serde")]
+mod journal;
 mod mappings;
 mod query;
 #[cfg(feature = "system-registry")]
 mod registry;
 mod resources;
 mod scheduler;
+#[cfg(feature = "serde")]
+mod serialization;
+mod split;
 mod system;
 mod try_default;
+mod view;
 
 pub use accessor::{EntityAccessor, QueryAccessor};
-pub use event::{CachedEventHandler, Event, EventHandler, EventId, RawEventHandler, Trigger};
-pub use query::{PreparedWorld, Query};
+pub use channel::{EventChannelHooks, EventReader};
+pub use dynamic::{
+    boxed_event_id, dynamic_event_id, BoxedEvent, DynamicEvent, DynamicEventHandler, DynamicEvents,
+    DynamicResources, RawTrigger,
+};
+pub use event::{
+    CachedEventHandler, Event, EventHandler, EventId, Propagation, RawEventHandler, Trigger,
+    UnhandledEventPolicy, UnhandledEvents,
+};
+pub use filter::{
+    Added, AddedChunks, ArchetypeAccess, ArchetypeElement, Changed, ChangedChunks, QueryFilter,
+    QueryTicks, TagValue, TagValueChunksets, Tagged, Untagged, With, Without,
+};
+#[cfg(feature = "serde")]
+pub use journal::{EventJournal, EventRegistry, JournalEntry};
+pub use query::{ComponentsMut, FilteredView, PreparedWorld, Query, ReadComponent, WriteComponent};
 #[cfg(feature = "system-registry")]
 pub use registry::*;
-pub use resources::{resource_id_for, resource_id_for_component, ResourceId, Resources};
-pub use scheduler::{EventsBuilder, Scheduler, SchedulerBuilder};
+pub use resources::{
+    resource_id_for, resource_id_for_component, resource_id_for_keyed, ResourceId, Resources,
+};
+pub use scheduler::{
+    CascadeError, EventsBuilder, Scheduler, SchedulerBuilder, DEFAULT_MAX_CASCADE_DEPTH,
+};
+#[cfg(feature = "serde")]
+pub use serialization::{ResourceRegistry, ResourcesView};
+pub use split::{QueryBorrow, Split, SplitQueries};
 pub use system::{
-    system_id_for, CachedSystem, MacroData, RawSystem, Read, System, SystemCtx, SystemData,
-    SystemDataOutput, SystemId, Write,
+    system_id_for, CachedSystem, ChangedRead, Concurrent, ConcurrentResource, Local, MacroData,
+    RawSystem, Read, System, SystemCtx, SystemData, SystemDataOutput, SystemId, Write,
 };
 pub use tonks_macros::{event_handler, system, Resource};
 pub use try_default::TryDefault;
+pub use view::{RequiredElements, TryRead, TryRefIter};
@@ -1,19 +1,21 @@
 //! Type-level query APIs as wrappers over Legion queries.
 
+use crate::cache::ArchetypeCache;
+use crate::filter::{ArchetypeAccess, QueryFilter, QueryTicks};
 use crate::system::SystemCtx;
+use crate::view::RequiredElements;
 use crate::{MacroData, ResourceId, Resources, SystemData, SystemDataOutput};
-use hashbrown::HashSet;
-use legion::borrow::{Ref, RefMut};
+use hashbrown::{HashMap, HashSet};
+use legion::borrow::{Ref, RefMapMut, RefMut};
 use legion::entity::Entity;
-use legion::filter::{
-    ArchetypeFilterData, ChunkFilterData, ChunksetFilterData, EntityFilter, Filter,
-};
-use legion::iterator::FissileIterator;
-use legion::query::{
-    Chunk, ChunkDataIter, ChunkEntityIter, ChunkViewIter, DefaultFilter, IntoQuery, ReadOnly, View,
-};
-use legion::storage::{Component, ComponentTypeId};
+use legion::filter::EntityFilter;
+use legion::query::{Chunk, DefaultFilter, IntoQuery, ReadOnly, View};
+use legion::storage::{Component, ComponentTypeId, Tag};
 use legion::world::World;
+use rayon::prelude::*;
+use std::marker::PhantomData;
+use std::ops::{BitAnd, Deref, DerefMut};
+use std::sync::Arc;
 
 /// A `legion::World` wrapper which can be safely passed to systems.
 pub struct PreparedWorld {
@@ -79,7 +81,7 @@ impl PreparedWorld {
     /// # Panics
     /// Panics if this system does not have read or write access to the component.
     /// To declare access to a component, add a query to the system which
-    /// accesses the component.
+    /// accesses the component, or a `ReadComponent`/`WriteComponent`.
     pub fn get_component<T: Component>(&self, entity: Entity) -> Option<Ref<T>> {
         assert!(self.read_components.contains(&ComponentTypeId::of::<T>()));
         unsafe { &*self.world }.get_component(entity)
@@ -90,7 +92,7 @@ impl PreparedWorld {
     /// # Panics
     /// Panics if this system does not have write access to the component.
     /// To declare access to a component, add a query to the system which
-    /// accesses the component.
+    /// accesses the component, or a `ReadComponent`/`WriteComponent`.
     pub fn get_component_mut<T: Component>(&mut self, entity: Entity) -> Option<RefMut<T>> {
         assert!(self.write_components.contains(&ComponentTypeId::of::<T>()));
         unsafe { self.get_component_mut_unchecked(entity) }
@@ -104,7 +106,7 @@ impl PreparedWorld {
     /// # Panics
     /// Panics if this system does not have read or write access to the component.
     /// To declare access to a component, add a query to the system which
-    /// accesses the component.
+    /// accesses the component, or a `ReadComponent`/`WriteComponent`.
     pub unsafe fn get_component_mut_unchecked<T: Component>(
         &self,
         entity: Entity,
@@ -117,6 +119,165 @@ impl PreparedWorld {
     pub fn is_alive(&self, entity: Entity) -> bool {
         unsafe { &*self.world }.is_alive(entity)
     }
+
+    /// Retrieves a component immutably for each of the given entities.
+    ///
+    /// # Panics
+    /// Panics if this system does not have read or write access to the component.
+    pub fn get_many<T: Component>(&self, entities: &[Entity]) -> Vec<Option<Ref<T>>> {
+        entities
+            .iter()
+            .map(|entity| self.get_component(*entity))
+            .collect()
+    }
+
+    /// Retrieves a component mutably for each of the given entities.
+    ///
+    /// Each chunk containing one of the components is borrowed once,
+    /// and the borrows are held until the returned `ComponentsMut` is dropped.
+    ///
+    /// # Panics
+    /// Panics if this system does not have write access to the component,
+    /// or if an entity is passed more than once, since the returned
+    /// references would alias.
+    pub fn get_many_mut<T: Component>(&mut self, entities: &[Entity]) -> ComponentsMut<T> {
+        assert!(self.write_components.contains(&ComponentTypeId::of::<T>()));
+        let world = unsafe { &*self.world };
+
+        let mut seen = HashSet::with_capacity(entities.len());
+        let mut borrows = vec![];
+        let mut chunks: HashMap<(usize, usize, usize), *mut T> = HashMap::new();
+        let mut components = Vec::with_capacity(entities.len());
+
+        for entity in entities {
+            assert!(
+                seen.insert(*entity),
+                "entity {:?} passed to `get_many_mut` more than once",
+                entity
+            );
+
+            let location = match world.get_entity_location(*entity) {
+                Some(location) => location,
+                None => {
+                    components.push(None);
+                    continue;
+                }
+            };
+
+            let key = (location.archetype(), location.set(), location.chunk());
+            let base = match chunks.get(&key) {
+                Some(base) => *base,
+                None => {
+                    let chunk = &world.storage().archetypes()[key.0].chunksets()[key.1][key.2];
+                    // Safety: the system has write access to `T`, and the
+                    // borrow is tracked by Legion until `ComponentsMut` is dropped.
+                    let mut slice = match chunk.components(ComponentTypeId::of::<T>()) {
+                        Some(set) => unsafe { set.data_slice_mut::<T>() },
+                        None => {
+                            components.push(None);
+                            continue;
+                        }
+                    };
+                    let base = slice.as_mut_ptr();
+                    borrows.push(slice);
+                    chunks.insert(key, base);
+                    base
+                }
+            };
+
+            // Safety: all references into a chunk are derived from the same
+            // slice, and the entities are distinct, so they do not alias.
+            components.push(Some(unsafe { &mut *base.add(location.component()) }));
+        }
+
+        ComponentsMut {
+            _borrows: borrows,
+            components,
+        }
+    }
+
+    /// Follows an entity reference stored in the `R` component of `entity`,
+    /// such as a parent or a target, and retrieves the `T` component of
+    /// the referenced entity.
+    ///
+    /// Returns `None` if either entity does not have the respective component.
+    ///
+    /// # Panics
+    /// Panics if this system does not have read access to both components.
+    pub fn follow<R, T>(
+        &self,
+        entity: Entity,
+        relation: impl FnOnce(&R) -> Entity,
+    ) -> Option<Ref<T>>
+    where
+        R: Component,
+        T: Component,
+    {
+        let target = relation(&*self.get_component::<R>(entity)?);
+        self.get_component(target)
+    }
+
+    /// Follows an entity reference stored in the `R` component of `entity`
+    /// and retrieves the `T` component of the referenced entity mutably,
+    /// along with the `R` component itself.
+    ///
+    /// Returns `None` if either entity does not have the respective component.
+    ///
+    /// # Panics
+    /// Panics if this system does not have read access to `R` and write access
+    /// to `T`. If `R` and `T` are the same type, panics if `entity` references
+    /// itself or another entity in the same chunk.
+    pub fn follow_mut<R, T>(
+        &mut self,
+        entity: Entity,
+        relation: impl FnOnce(&R) -> Entity,
+    ) -> Option<(Ref<R>, RefMut<T>)>
+    where
+        R: Component,
+        T: Component,
+    {
+        let source = self.get_component::<R>(entity)?;
+        let target = relation(&*source);
+
+        if ComponentTypeId::of::<R>() == ComponentTypeId::of::<T>() {
+            assert_ne!(
+                entity, target,
+                "entity {:?} references itself through `follow_mut`",
+                entity
+            );
+        }
+
+        assert!(self.write_components.contains(&ComponentTypeId::of::<T>()));
+        // Safety: `&mut self` prevents any other access through this world,
+        // and Legion tracks the borrows of both components.
+        let target = unsafe { self.get_component_mut_unchecked::<T>(target)? };
+        Some((source, target))
+    }
+}
+
+/// Components of several entities borrowed mutably at once.
+/// Created by `PreparedWorld::get_many_mut`.
+///
+/// Dereferences to a slice with one element for each entity,
+/// which is `None` if the entity does not have the component.
+pub struct ComponentsMut<'a, T: Component> {
+    /// Borrows of the chunks containing the components.
+    _borrows: Vec<RefMapMut<'a, &'a mut [T]>>,
+    components: Vec<Option<&'a mut T>>,
+}
+
+impl<'a, T: Component> Deref for ComponentsMut<'a, T> {
+    type Target = [Option<&'a mut T>];
+
+    fn deref(&self) -> &Self::Target {
+        &self.components
+    }
+}
+
+impl<'a, T: Component> DerefMut for ComponentsMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.components
+    }
 }
 
 impl<'a> SystemDataOutput<'a> for &'a mut PreparedWorld {
@@ -127,18 +288,168 @@ impl MacroData for &'static mut PreparedWorld {
     type SystemData = PreparedWorld;
 }
 
-/// System data which allows for querying entities.
-pub struct Query<V>
+/// System data which declares read access to components of type `T`
+/// for random access through `PreparedWorld`, without iterating a query.
+pub struct ReadComponent<T>(PhantomData<T>)
 where
-    V: for<'v> View<'v> + DefaultFilter,
+    T: Component;
+
+impl<'a, T> SystemData<'a> for ReadComponent<T>
+where
+    T: Component,
+{
+    type Output = &'a Self;
+
+    unsafe fn load_from_resources(
+        _resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        Self(PhantomData)
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![ComponentTypeId::of::<T>()]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self
+    }
+}
+
+impl<'a, T> SystemDataOutput<'a> for &'a ReadComponent<T>
+where
+    T: Component,
+{
+    type SystemData = ReadComponent<T>;
+}
+
+impl<T> MacroData for &'static ReadComponent<T>
+where
+    T: Component,
 {
-    query: legion::query::Query<V, <V as DefaultFilter>::Filter>,
+    type SystemData = ReadComponent<T>;
 }
 
-impl<'a, V> SystemData<'a> for Query<V>
+/// System data which declares write access to components of type `T`
+/// for random access through `PreparedWorld`, without iterating a query.
+pub struct WriteComponent<T>(PhantomData<T>)
+where
+    T: Component;
+
+impl<'a, T> SystemData<'a> for WriteComponent<T>
+where
+    T: Component,
+{
+    type Output = &'a Self;
+
+    unsafe fn load_from_resources(
+        _resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        Self(PhantomData)
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![ComponentTypeId::of::<T>()]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![ComponentTypeId::of::<T>()]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self
+    }
+}
+
+impl<'a, T> SystemDataOutput<'a> for &'a WriteComponent<T>
+where
+    T: Component,
+{
+    type SystemData = WriteComponent<T>;
+}
+
+impl<T> MacroData for &'static WriteComponent<T>
+where
+    T: Component,
+{
+    type SystemData = WriteComponent<T>;
+}
+
+/// A `View` combined with a `QueryFilter`.
+///
+/// This is implemented for all views whose default filter
+/// can be combined with the filter.
+pub trait FilteredView<F>: for<'v> View<'v> + DefaultFilter
+where
+    F: QueryFilter,
+{
+    /// The default filter of the view combined with the filter.
+    type Combined: EntityFilter + Send + Sync;
+
+    /// Creates a Legion query for this view with the filter applied.
+    fn filtered_query(ticks: &Arc<QueryTicks>) -> legion::query::Query<Self, Self::Combined>;
+}
+
+impl<V, F> FilteredView<F> for V
 where
     V: for<'v> View<'v> + DefaultFilter,
-    <V as DefaultFilter>::Filter: Send + Sync + 'a,
+    F: QueryFilter,
+    <V as DefaultFilter>::Filter: BitAnd<F::Filter>,
+    <<V as DefaultFilter>::Filter as BitAnd<F::Filter>>::Output: EntityFilter + Send + Sync,
+{
+    type Combined = <<V as DefaultFilter>::Filter as BitAnd<F::Filter>>::Output;
+
+    fn filtered_query(ticks: &Arc<QueryTicks>) -> legion::query::Query<Self, Self::Combined> {
+        V::query().filter(F::filter(ticks))
+    }
+}
+
+/// System data which allows for querying entities.
+///
+/// The optional `QueryFilter` `F` restricts the entities
+/// yielded by the query, e.g. `Query<Read<Position>, Changed<Position>>`.
+///
+/// The archetypes matched by the query are cached between runs, so iterating
+/// only evaluates archetypes created since the previous call.
+pub struct Query<V, F = ()>
+where
+    V: FilteredView<F>,
+    F: QueryFilter,
+{
+    query: legion::query::Query<V, <V as FilteredView<F>>::Combined>,
+    /// Change-detection state shared with the filter.
+    ticks: Arc<QueryTicks>,
+    /// Archetypes matched by the query so far.
+    archetypes: ArchetypeCache,
+}
+
+impl<'a, V, F> SystemData<'a> for Query<V, F>
+where
+    V: FilteredView<F>,
+    F: QueryFilter,
 {
     type Output = &'a mut Self;
 
@@ -147,7 +458,24 @@ where
         _ctx: SystemCtx,
         _world: &World,
     ) -> Self {
-        Self { query: V::query() }
+        let ticks = Arc::new(QueryTicks::default());
+        Self {
+            query: V::filtered_query(&ticks),
+            ticks,
+            archetypes: ArchetypeCache::default(),
+        }
+    }
+
+    fn validate() {
+        // The query's own writes would otherwise be detected on each run.
+        let writes = V::write_types();
+        for component in F::changed() {
+            assert!(
+                !writes.contains(&component),
+                "query {} writes a component filtered by `Changed`",
+                std::any::type_name::<Self>()
+            );
+        }
     }
 
     fn resource_reads() -> Vec<ResourceId> {
@@ -159,38 +487,59 @@ where
     }
 
     fn component_reads() -> Vec<ComponentTypeId> {
-        V::read_types()
+        let mut reads = V::read_types();
+        reads.extend(F::component_reads());
+        reads
     }
 
     fn component_writes() -> Vec<ComponentTypeId> {
         V::write_types()
     }
 
+    fn archetype_accesses() -> Vec<ArchetypeAccess> {
+        let mut required = V::required();
+        required.extend(F::required());
+        vec![ArchetypeAccess {
+            reads: Self::component_reads(),
+            writes: Self::component_writes(),
+            required,
+            excluded: F::excluded(),
+        }]
+    }
+
     fn before_execution(&'a mut self) -> Self::Output {
+        self.ticks.advance();
         self
     }
 }
 
-impl<'a, V> SystemDataOutput<'a> for &'a mut Query<V>
+impl<'a, V, F> SystemDataOutput<'a> for &'a mut Query<V, F>
 where
-    V: for<'v> View<'v> + DefaultFilter,
-    <V as DefaultFilter>::Filter: Send + Sync + 'a,
+    V: FilteredView<F>,
+    F: QueryFilter,
 {
-    type SystemData = Query<V>;
+    type SystemData = Query<V, F>;
 }
 
-impl<V> MacroData for &'static mut Query<V>
+impl<V, F> MacroData for &'static mut Query<V, F>
 where
-    V: for<'v> View<'v> + DefaultFilter,
-    <V as DefaultFilter>::Filter: Send + Sync,
+    V: FilteredView<F>,
+    F: QueryFilter,
 {
-    type SystemData = Query<V>;
+    type SystemData = Query<V, F>;
 }
 
-impl<V> Query<V>
+impl<V, F> Query<V, F>
 where
-    V: for<'v> View<'v> + DefaultFilter,
+    V: FilteredView<F>,
+    F: QueryFilter,
 {
+    /// Sets the value matched by a `TagValue<T>` filter of this query.
+    /// Until a value is set, no entities match.
+    pub fn set_tag_value<T: Tag>(&mut self, value: T) {
+        self.ticks.set_tag_value(value);
+    }
+
     // Implementations "borrowed" from Legion's codebase with a few modifications, licensed under MIT.
     // Don't blame me—I'm not going to write all this!
 
@@ -208,30 +557,24 @@ where
     pub unsafe fn iter_chunks_unchecked<'b, 'c>(
         &'c mut self,
         world: &PreparedWorld,
-    ) -> ChunkViewIter<
-        'b,
-        'c,
-        V,
-        <<V as DefaultFilter>::Filter as EntityFilter>::ArchetypeFilter,
-        <<V as DefaultFilter>::Filter as EntityFilter>::ChunksetFilter,
-        <<V as DefaultFilter>::Filter as EntityFilter>::ChunkFilter,
-    > {
-        self.query.iter_chunks_unchecked(&*world.world)
+    ) -> impl Iterator<Item = Chunk<'b, V>> + 'c
+    where
+        'b: 'c,
+    {
+        let world: &'b World = &*world.world;
+        self.archetypes.update(world, &self.query.filter);
+        self.archetypes
+            .chunks(world, &self.query.filter)
+            .map(|(archetype, set_index, chunk)| Chunk::new(archetype, set_index, chunk))
     }
 
     /// Gets an iterator which iterates through all chunks that match the query.
     pub fn iter_chunks_immutable<'b, 'c>(
         &'c mut self,
         world: &PreparedWorld,
-    ) -> ChunkViewIter<
-        'b,
-        'c,
-        V,
-        <<V as DefaultFilter>::Filter as EntityFilter>::ArchetypeFilter,
-        <<V as DefaultFilter>::Filter as EntityFilter>::ChunksetFilter,
-        <<V as DefaultFilter>::Filter as EntityFilter>::ChunkFilter,
-    >
+    ) -> impl Iterator<Item = Chunk<'b, V>> + 'c
     where
+        'b: 'c,
         V: ReadOnly,
     {
         // safe because the view can only read data immutably
@@ -242,14 +585,10 @@ where
     pub fn iter_chunks<'b, 'c>(
         &'c mut self,
         world: &mut PreparedWorld,
-    ) -> ChunkViewIter<
-        'b,
-        'c,
-        V,
-        <<V as DefaultFilter>::Filter as EntityFilter>::ArchetypeFilter,
-        <<V as DefaultFilter>::Filter as EntityFilter>::ChunksetFilter,
-        <<V as DefaultFilter>::Filter as EntityFilter>::ChunkFilter,
-    > {
+    ) -> impl Iterator<Item = Chunk<'b, V>> + 'c
+    where
+        'b: 'c,
+    {
         // safe because the &mut PreparedWorld ensures exclusivity
         unsafe { self.iter_chunks_unchecked(world) }
     }
@@ -265,42 +604,34 @@ where
     ///
     /// This function may panic if other code is concurrently accessing the same components.
     #[inline]
-    pub unsafe fn iter_entities_unchecked<'b, 'c>(
-        &'c mut self,
+    pub unsafe fn iter_entities_unchecked<'b, 'data>(
+        &'b mut self,
         world: &PreparedWorld,
-    ) -> ChunkEntityIter<
-        'b,
-        V,
-        ChunkViewIter<
-            'b,
-            'c,
-            V,
-            <<V as DefaultFilter>::Filter as EntityFilter>::ArchetypeFilter,
-            <<V as DefaultFilter>::Filter as EntityFilter>::ChunksetFilter,
-            <<V as DefaultFilter>::Filter as EntityFilter>::ChunkFilter,
-        >,
-    > {
-        self.query.iter_entities_unchecked(&*world.world)
+    ) -> impl Iterator<Item = (Entity, <<V as View<'data>>::Iter as Iterator>::Item)> + 'b
+    where
+        'data: 'b,
+    {
+        let world: &'data World = &*world.world;
+        self.archetypes.update(world, &self.query.filter);
+        self.archetypes.chunks(world, &self.query.filter).flat_map(
+            |(archetype, set_index, chunk)| {
+                chunk
+                    .entities()
+                    .iter()
+                    .copied()
+                    .zip(V::fetch(archetype, chunk, set_index))
+            },
+        )
     }
 
     /// Gets an iterator which iterates through all entity data that matches the query, and also yields the the `Entity` IDs.
     #[inline]
-    pub fn iter_entities_immutable<'b, 'c>(
-        &'c mut self,
+    pub fn iter_entities_immutable<'b, 'data>(
+        &'b mut self,
         world: &PreparedWorld,
-    ) -> ChunkEntityIter<
-        'b,
-        V,
-        ChunkViewIter<
-            'b,
-            'c,
-            V,
-            <<V as DefaultFilter>::Filter as EntityFilter>::ArchetypeFilter,
-            <<V as DefaultFilter>::Filter as EntityFilter>::ChunksetFilter,
-            <<V as DefaultFilter>::Filter as EntityFilter>::ChunkFilter,
-        >,
-    >
+    ) -> impl Iterator<Item = (Entity, <<V as View<'data>>::Iter as Iterator>::Item)> + 'b
     where
+        'data: 'b,
         V: ReadOnly,
     {
         // safe because the view can only read data immutably
@@ -309,21 +640,13 @@ where
 
     /// Gets an iterator which iterates through all entity data that matches the query, and also yields the the `Entity` IDs.
     #[inline]
-    pub fn iter_entities<'b, 'c>(
-        &'c mut self,
+    pub fn iter_entities<'b, 'data>(
+        &'b mut self,
         world: &mut PreparedWorld,
-    ) -> ChunkEntityIter<
-        'b,
-        V,
-        ChunkViewIter<
-            'b,
-            'c,
-            V,
-            <<V as DefaultFilter>::Filter as EntityFilter>::ArchetypeFilter,
-            <<V as DefaultFilter>::Filter as EntityFilter>::ChunksetFilter,
-            <<V as DefaultFilter>::Filter as EntityFilter>::ChunkFilter,
-        >,
-    > {
+    ) -> impl Iterator<Item = (Entity, <<V as View<'data>>::Iter as Iterator>::Item)> + 'b
+    where
+        'data: 'b,
+    {
         // safe because the &mut PreparedWorld ensures exclusivity
         unsafe { self.iter_entities_unchecked(world) }
     }
@@ -342,19 +665,15 @@ where
     pub unsafe fn iter_unchecked<'b, 'data>(
         &'b mut self,
         world: &PreparedWorld,
-    ) -> ChunkDataIter<
-        'data,
-        V,
-        ChunkViewIter<
-            'data,
-            'b,
-            V,
-            <<V as DefaultFilter>::Filter as EntityFilter>::ArchetypeFilter,
-            <<V as DefaultFilter>::Filter as EntityFilter>::ChunksetFilter,
-            <<V as DefaultFilter>::Filter as EntityFilter>::ChunkFilter,
-        >,
-    > {
-        self.query.iter_unchecked(&*world.world)
+    ) -> impl Iterator<Item = <<V as View<'data>>::Iter as Iterator>::Item> + 'b
+    where
+        'data: 'b,
+    {
+        let world: &'data World = &*world.world;
+        self.archetypes.update(world, &self.query.filter);
+        self.archetypes
+            .chunks(world, &self.query.filter)
+            .flat_map(|(archetype, set_index, chunk)| V::fetch(archetype, chunk, set_index))
     }
 
     /// Gets an iterator which iterates through all entity data that matches the query.
@@ -362,19 +681,9 @@ where
     pub fn iter_immutable<'b, 'data>(
         &'b mut self,
         world: &PreparedWorld,
-    ) -> ChunkDataIter<
-        'data,
-        V,
-        ChunkViewIter<
-            'data,
-            'b,
-            V,
-            <<V as DefaultFilter>::Filter as EntityFilter>::ArchetypeFilter,
-            <<V as DefaultFilter>::Filter as EntityFilter>::ChunksetFilter,
-            <<V as DefaultFilter>::Filter as EntityFilter>::ChunkFilter,
-        >,
-    >
+    ) -> impl Iterator<Item = <<V as View<'data>>::Iter as Iterator>::Item> + 'b
     where
+        'data: 'b,
         V: ReadOnly,
     {
         // safe because the view can only read data immutably
@@ -386,18 +695,10 @@ where
     pub fn iter<'b, 'data>(
         &'b mut self,
         world: &mut PreparedWorld,
-    ) -> ChunkDataIter<
-        'data,
-        V,
-        ChunkViewIter<
-            'data,
-            'b,
-            V,
-            <<V as DefaultFilter>::Filter as EntityFilter>::ArchetypeFilter,
-            <<V as DefaultFilter>::Filter as EntityFilter>::ChunksetFilter,
-            <<V as DefaultFilter>::Filter as EntityFilter>::ChunkFilter,
-        >,
-    > {
+    ) -> impl Iterator<Item = <<V as View<'data>>::Iter as Iterator>::Item> + 'b
+    where
+        'data: 'b,
+    {
         // safe because the &mut PreparedWorld ensures exclusivity
         unsafe { self.iter_unchecked(world) }
     }
@@ -417,7 +718,11 @@ where
     where
         T: Fn(<<V as View<'data>>::Iter as Iterator>::Item),
     {
-        self.query.for_each_unchecked(&*world.world, f)
+        let world: &'data World = &*world.world;
+        self.archetypes.update(world, &self.query.filter);
+        for (archetype, set_index, chunk) in self.archetypes.chunks(world, &self.query.filter) {
+            V::fetch(archetype, chunk, set_index).for_each(&f);
+        }
     }
 
     /// Iterates through all entity data that matches the query.
@@ -459,7 +764,16 @@ where
     ) where
         T: Fn((Entity, <<V as View<'data>>::Iter as Iterator>::Item)),
     {
-        self.query.for_each_entities_unchecked(&*world.world, f)
+        let world: &'data World = &*world.world;
+        self.archetypes.update(world, &self.query.filter);
+        for (archetype, set_index, chunk) in self.archetypes.chunks(world, &self.query.filter) {
+            chunk
+                .entities()
+                .iter()
+                .copied()
+                .zip(V::fetch(archetype, chunk, set_index))
+                .for_each(&f);
+        }
     }
 
     /// Iterates through all entity data that matches the query.
@@ -497,17 +811,20 @@ where
     pub unsafe fn par_entities_for_each_unchecked<'b, T>(&'b mut self, world: &PreparedWorld, f: T)
     where
         T: Fn((Entity, <<V as View<'b>>::Iter as Iterator>::Item)) + Send + Sync,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ArchetypeFilter as Filter<
-            ArchetypeFilterData<'b>,
-        >>::Iter: FissileIterator,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ChunksetFilter as Filter<
-            ChunksetFilterData<'b>,
-        >>::Iter: FissileIterator,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ChunkFilter as Filter<
-            ChunkFilterData<'b>,
-        >>::Iter: FissileIterator,
     {
-        self.query.par_entities_for_each_unchecked(&*world.world, f)
+        let world: &'b World = &*world.world;
+        self.archetypes.update(world, &self.query.filter);
+        let chunks: Vec<_> = self.archetypes.chunks(world, &self.query.filter).collect();
+        chunks
+            .into_par_iter()
+            .for_each(|(archetype, set_index, chunk)| {
+                chunk
+                    .entities()
+                    .iter()
+                    .copied()
+                    .zip(V::fetch(archetype, chunk, set_index))
+                    .for_each(&f);
+            });
     }
 
     /// Iterates through all entities that matches the query in parallel by chunk.
@@ -515,15 +832,6 @@ where
     pub fn par_entities_for_each_immutable<'b, T>(&'b mut self, world: &PreparedWorld, f: T)
     where
         T: Fn((Entity, <<V as View<'b>>::Iter as Iterator>::Item)) + Send + Sync,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ArchetypeFilter as Filter<
-            ArchetypeFilterData<'b>,
-        >>::Iter: FissileIterator,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ChunksetFilter as Filter<
-            ChunksetFilterData<'b>,
-        >>::Iter: FissileIterator,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ChunkFilter as Filter<
-            ChunkFilterData<'b>,
-        >>::Iter: FissileIterator,
         V: ReadOnly,
     {
         // safe because the view can only read data immutably
@@ -535,15 +843,6 @@ where
     pub fn par_entities_for_each<'b, T>(&'b mut self, world: &mut PreparedWorld, f: T)
     where
         T: Fn((Entity, <<V as View<'b>>::Iter as Iterator>::Item)) + Send + Sync,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ArchetypeFilter as Filter<
-            ArchetypeFilterData<'b>,
-        >>::Iter: FissileIterator,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ChunksetFilter as Filter<
-            ChunksetFilterData<'b>,
-        >>::Iter: FissileIterator,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ChunkFilter as Filter<
-            ChunkFilterData<'b>,
-        >>::Iter: FissileIterator,
     {
         // safe because the &mut PreparedWorld ensures exclusivity
         unsafe { self.par_entities_for_each_unchecked(world, f) }
@@ -563,17 +862,15 @@ where
     pub unsafe fn par_for_each_unchecked<'b, T>(&'b mut self, world: &PreparedWorld, f: T)
     where
         T: Fn(<<V as View<'b>>::Iter as Iterator>::Item) + Send + Sync,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ArchetypeFilter as Filter<
-            ArchetypeFilterData<'b>,
-        >>::Iter: FissileIterator,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ChunksetFilter as Filter<
-            ChunksetFilterData<'b>,
-        >>::Iter: FissileIterator,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ChunkFilter as Filter<
-            ChunkFilterData<'b>,
-        >>::Iter: FissileIterator,
     {
-        self.query.par_for_each_unchecked(&*world.world, f)
+        let world: &'b World = &*world.world;
+        self.archetypes.update(world, &self.query.filter);
+        let chunks: Vec<_> = self.archetypes.chunks(world, &self.query.filter).collect();
+        chunks
+            .into_par_iter()
+            .for_each(|(archetype, set_index, chunk)| {
+                V::fetch(archetype, chunk, set_index).for_each(&f);
+            });
     }
 
     /// Iterates through all entity data that matches the query in parallel.
@@ -581,15 +878,6 @@ where
     pub fn par_for_each_immutable<'b, T>(&'b mut self, world: &PreparedWorld, f: T)
     where
         T: Fn(<<V as View<'b>>::Iter as Iterator>::Item) + Send + Sync,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ArchetypeFilter as Filter<
-            ArchetypeFilterData<'b>,
-        >>::Iter: FissileIterator,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ChunksetFilter as Filter<
-            ChunksetFilterData<'b>,
-        >>::Iter: FissileIterator,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ChunkFilter as Filter<
-            ChunkFilterData<'b>,
-        >>::Iter: FissileIterator,
         V: ReadOnly,
     {
         // safe because the view can only read data immutably
@@ -601,15 +889,6 @@ where
     pub fn par_for_each<'b, T>(&'b mut self, world: &mut PreparedWorld, f: T)
     where
         T: Fn(<<V as View<'b>>::Iter as Iterator>::Item) + Send + Sync,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ArchetypeFilter as Filter<
-            ArchetypeFilterData<'b>,
-        >>::Iter: FissileIterator,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ChunksetFilter as Filter<
-            ChunksetFilterData<'b>,
-        >>::Iter: FissileIterator,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ChunkFilter as Filter<
-            ChunkFilterData<'b>,
-        >>::Iter: FissileIterator,
     {
         // safe because the &mut PreparedWorld ensures exclusivity
         unsafe { self.par_for_each_unchecked(world, f) }
@@ -629,17 +908,13 @@ where
     pub unsafe fn par_for_each_chunk_unchecked<'b, T>(&'b mut self, world: &PreparedWorld, f: T)
     where
         T: Fn(Chunk<'b, V>) + Send + Sync,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ArchetypeFilter as Filter<
-            ArchetypeFilterData<'b>,
-        >>::Iter: FissileIterator,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ChunksetFilter as Filter<
-            ChunksetFilterData<'b>,
-        >>::Iter: FissileIterator,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ChunkFilter as Filter<
-            ChunkFilterData<'b>,
-        >>::Iter: FissileIterator,
     {
-        self.query.par_for_each_chunk_unchecked(&*world.world, f)
+        let world: &'b World = &*world.world;
+        self.archetypes.update(world, &self.query.filter);
+        let chunks: Vec<_> = self.archetypes.chunks(world, &self.query.filter).collect();
+        chunks
+            .into_par_iter()
+            .for_each(|(archetype, set_index, chunk)| f(Chunk::new(archetype, set_index, chunk)));
     }
 
     /// Gets a parallel iterator of chunks that match the query.
@@ -647,15 +922,6 @@ where
     pub fn par_for_each_chunk_immutable<'b, T>(&'b mut self, world: &PreparedWorld, f: T)
     where
         T: Fn(Chunk<'b, V>) + Send + Sync,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ArchetypeFilter as Filter<
-            ArchetypeFilterData<'b>,
-        >>::Iter: FissileIterator,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ChunksetFilter as Filter<
-            ChunksetFilterData<'b>,
-        >>::Iter: FissileIterator,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ChunkFilter as Filter<
-            ChunkFilterData<'b>,
-        >>::Iter: FissileIterator,
         V: ReadOnly,
     {
         // safe because the view can only read data immutably
@@ -667,15 +933,6 @@ where
     pub fn par_for_each_chunk<'b, T>(&'b mut self, world: &mut PreparedWorld, f: T)
     where
         T: Fn(Chunk<'b, V>) + Send + Sync,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ArchetypeFilter as Filter<
-            ArchetypeFilterData<'b>,
-        >>::Iter: FissileIterator,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ChunksetFilter as Filter<
-            ChunksetFilterData<'b>,
-        >>::Iter: FissileIterator,
-        <<<V as DefaultFilter>::Filter as EntityFilter>::ChunkFilter as Filter<
-            ChunkFilterData<'b>,
-        >>::Iter: FissileIterator,
     {
         // safe because the &mut PreparedWorld ensures exclusivity
         unsafe { self.par_for_each_chunk_unchecked(world, f) }
@@ -9,10 +9,12 @@ use parking_lot::Mutex;
 use std::any::TypeId;
 use std::cell::UnsafeCell;
 use std::iter;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum Type {
-    Resource(TypeId),
+    /// A resource type along with the type of its key.
+    Resource(TypeId, TypeId),
     Component(ComponentTypeId),
 }
 
@@ -32,10 +34,21 @@ lazy_static! {
 }
 
 /// Returns the resource ID corresponding to a given type.
+///
+/// This is equivalent to `resource_id_for_keyed::<T, ()>()`.
 pub fn resource_id_for<T: Resource>() -> ResourceId {
+    resource_id_for_keyed::<T, ()>()
+}
+
+/// Returns the resource ID corresponding to a given type and key.
+///
+/// Keys allow for multiple independent instances of the same
+/// resource type. The key is an arbitrary marker type; each
+/// distinct key results in a distinct `ResourceId`.
+pub fn resource_id_for_keyed<T: Resource, K: 'static>() -> ResourceId {
     RESOURCE_ID_MAPPINGS
         .lock()
-        .get_or_alloc(Type::Resource(TypeId::of::<T>()))
+        .get_or_alloc(Type::Resource(TypeId::of::<T>(), TypeId::of::<K>()))
 }
 
 /// Returns the resource ID corresponding to a component type.
@@ -55,11 +68,64 @@ mod mopafy {
     mopafy!(Resource);
 }
 
+/// Pointers into the change tracking state of a single resource.
+///
+/// This allows `Write` to record modifications without holding
+/// a reference to the `Resources`. Both values are boxed, so the pointers
+/// remain valid for as long as the `Resources` is alive, even if it is moved.
+#[derive(Clone, Copy)]
+pub(crate) struct ChangeTracker {
+    /// The `Resources`-wide change counter.
+    counter: *const AtomicU64,
+    /// The change tick of this resource.
+    tick: *const AtomicU64,
+}
+
+impl ChangeTracker {
+    /// Records a modification of the resource, setting its
+    /// change tick to a newly allocated value of the change counter.
+    ///
+    /// # Safety
+    /// The `Resources` this tracker was obtained from must still be alive.
+    pub(crate) unsafe fn mark_changed(&self) {
+        let tick = (&*self.counter).fetch_add(1, Ordering::AcqRel) + 1;
+        (&*self.tick).store(tick, Ordering::Release);
+    }
+
+    /// Returns the change tick of the resource.
+    ///
+    /// # Safety
+    /// The `Resources` this tracker was obtained from must still be alive.
+    pub(crate) unsafe fn tick(&self) -> u64 {
+        (&*self.tick).load(Ordering::Acquire)
+    }
+
+    /// Returns the current value of the change counter. Any later
+    /// modification will result in a change tick greater than this value.
+    ///
+    /// # Safety
+    /// The `Resources` this tracker was obtained from must still be alive.
+    pub(crate) unsafe fn current(&self) -> u64 {
+        (&*self.counter).load(Ordering::Acquire)
+    }
+}
+
 /// Stores resources. Resource borrow access is unchecked,
 /// so most functions are unsafe.
+///
+/// Each resource has a _change tick_, which is the value of a
+/// monotonically increasing change counter at the time the resource
+/// was last inserted or mutably accessed.
 pub struct Resources {
     /// Stored resources, accessed by the `ResourceId` index.
     resources: Vec<UnsafeCell<Option<Box<dyn Resource>>>>,
+    /// Change tick for each resource, accessed by the `ResourceId` index.
+    ///
+    /// These are boxed so that `ChangeTracker`s remain valid when
+    /// this vector is extended.
+    change_ticks: Vec<Box<AtomicU64>>,
+    /// Counter used to allocate change ticks.
+    change_counter: Box<AtomicU64>,
 }
 
 unsafe impl Send for Resources {}
@@ -67,7 +133,11 @@ unsafe impl Sync for Resources {}
 
 impl Default for Resources {
     fn default() -> Self {
-        Self { resources: vec![] }
+        Self {
+            resources: vec![],
+            change_ticks: vec![],
+            change_counter: Box::new(AtomicU64::new(0)),
+        }
     }
 }
 
@@ -82,7 +152,25 @@ impl Resources {
     /// # Panics
     /// Panics if the resource does not exist.
     pub fn get<T: Resource>(&self) -> &T {
-        unsafe { self.get_unchecked(resource_id_for::<T>()) }
+        self.get_keyed::<T, ()>()
+    }
+
+    /// Returns a reference to the resource with the given key.
+    ///
+    /// # Panics
+    /// Panics if the resource does not exist.
+    pub fn get_keyed<T: Resource, K: 'static>(&self) -> &T {
+        unsafe { self.get_unchecked(resource_id_for_keyed::<T, K>()) }
+    }
+
+    /// Returns a reference to the resource, or `None`
+    /// if it does not exist.
+    pub fn try_get<T: Resource>(&self) -> Option<&T> {
+        let cell = self.resources.get(resource_id_for::<T>().0)?;
+        // Safety: borrow rules are enforced through &self.
+        unsafe { &*cell.get() }
+            .as_ref()
+            .map(|resource| resource.as_ref().downcast_ref().unwrap())
     }
 
     /// Returns a mutable reference to the resource.
@@ -90,8 +178,21 @@ impl Resources {
     /// # Panics
     /// Panics if the resource does not exist.
     pub fn get_mut<T: Resource>(&mut self) -> &mut T {
+        self.get_mut_keyed::<T, ()>()
+    }
+
+    /// Returns a mutable reference to the resource with the given key.
+    ///
+    /// # Panics
+    /// Panics if the resource does not exist.
+    pub fn get_mut_keyed<T: Resource, K: 'static>(&mut self) -> &mut T {
+        let id = resource_id_for_keyed::<T, K>();
         // Safety: borrow rules are enforced through &mut self.
-        unsafe { self.get_mut_unchecked(resource_id_for::<T>()) }
+        unsafe {
+            let resource = self.get_mut_unchecked(id);
+            self.change_tracker(id).mark_changed();
+            resource
+        }
     }
 
     /// Returns a reference to the resource with the given ID.
@@ -101,9 +202,9 @@ impl Resources {
     /// Care must be taken to ensure that borrowing rules are followed.
     ///
     /// In addition, the type of the resource being requested must match
-    /// the ID. (This is checked in debug mode.)
+    /// the ID or one of its keyed IDs. (This is checked against the type
+    /// of the stored resource.)
     pub unsafe fn get_unchecked<T: Resource>(&self, id: ResourceId) -> &T {
-        debug_assert_eq!(resource_id_for::<T>(), id);
         ((&*self
             .resources
             .get(id.0)
@@ -119,7 +220,13 @@ impl Resources {
             )))
         .as_ref()
         .downcast_ref()
-        .unwrap()
+        .unwrap_or_else(|| {
+            panic!(
+                "resource ID {:?} does not belong to type {}",
+                id,
+                std::any::type_name::<T>()
+            )
+        })
     }
 
     /// Returns a mutable reference to the resource with the given ID.
@@ -129,11 +236,10 @@ impl Resources {
     /// Care must be taken to ensure that borrowing rules are followed.
     ///
     /// In addition, the type of the resource being requested must match
-    /// the ID. (This is checked in debug mode.)
+    /// the ID or one of its keyed IDs. (This is checked against the type
+    /// of the stored resource.)
     #[allow(clippy::mut_from_ref)] // Function is unsafe: users are responsible for this.
     pub unsafe fn get_mut_unchecked<T: Resource>(&self, id: ResourceId) -> &mut T {
-        debug_assert_eq!(resource_id_for::<T>(), id);
-
         (self
             .resources
             .get(id.0)
@@ -151,28 +257,80 @@ impl Resources {
             )))
         .as_mut()
         .downcast_mut()
-        .unwrap()
+        .unwrap_or_else(|| {
+            panic!(
+                "resource ID {:?} does not belong to type {}",
+                id,
+                std::any::type_name::<T>()
+            )
+        })
     }
 
     /// Inserts a resource of the given type, replacing
     /// the old resource if it exists.
     pub fn insert<T: Resource>(&mut self, value: T) {
-        let id = resource_id_for::<T>();
+        self.insert_keyed::<T, ()>(value);
+    }
 
-        if self.resources.len() <= id.0 {
-            // Extend resources vector
-            self.resources.extend(
-                iter::repeat_with(|| UnsafeCell::new(None)).take(id.0 - self.resources.len() + 1),
-            );
-        }
+    /// Inserts a resource of the given type with the given key,
+    /// replacing the old resource if it exists.
+    pub fn insert_keyed<T: Resource, K: 'static>(&mut self, value: T) {
+        let id = resource_id_for_keyed::<T, K>();
+
+        self.extend_to(id);
 
         self.resources[id.0] = UnsafeCell::new(Some(Box::new(value)));
+        unsafe { self.change_tracker(id).mark_changed() };
     }
 
     /// Inserts a resource if it is absent.
     pub fn insert_if_absent<T: Resource>(&mut self, value: T) {
-        let id = resource_id_for::<T>();
+        self.insert_if_absent_keyed::<T, ()>(value);
+    }
+
+    /// Inserts a resource with the given key if it is absent.
+    pub fn insert_if_absent_keyed<T: Resource, K: 'static>(&mut self, value: T) {
+        let id = resource_id_for_keyed::<T, K>();
 
+        self.extend_to(id);
+
+        let resource = unsafe { &mut *self.resources[id.0].get() };
+        if resource.is_some() {
+            return;
+        }
+        self.resources[id.0] = UnsafeCell::new(Some(Box::new(value)));
+        unsafe { self.change_tracker(id).mark_changed() };
+    }
+
+    /// Returns the change tick of the resource with the given ID,
+    /// or 0 if it has never been inserted.
+    pub fn change_tick(&self, id: ResourceId) -> u64 {
+        self.change_ticks
+            .get(id.0)
+            .map(|tick| tick.load(Ordering::Acquire))
+            .unwrap_or(0)
+    }
+
+    /// Returns the current value of the change counter. A resource
+    /// whose change tick is greater than a previously observed value
+    /// of this counter has changed since that observation.
+    pub fn current_tick(&self) -> u64 {
+        self.change_counter.load(Ordering::Acquire)
+    }
+
+    /// Returns a `ChangeTracker` for the resource with the given ID.
+    ///
+    /// # Panics
+    /// Panics if the resource has never been inserted.
+    pub(crate) fn change_tracker(&self, id: ResourceId) -> ChangeTracker {
+        ChangeTracker {
+            counter: &*self.change_counter as *const AtomicU64,
+            tick: &*self.change_ticks[id.0] as *const AtomicU64,
+        }
+    }
+
+    /// Extends the internal vectors so that they can be indexed by `id`.
+    fn extend_to(&mut self, id: ResourceId) {
         if self.resources.len() <= id.0 {
             // Extend resources vector
             self.resources.extend(
@@ -180,11 +338,12 @@ impl Resources {
             );
         }
 
-        let resource = unsafe { &mut *self.resources[id.0].get() };
-        if resource.is_some() {
-            return;
+        if self.change_ticks.len() <= id.0 {
+            self.change_ticks.extend(
+                iter::repeat_with(|| Box::new(AtomicU64::new(0)))
+                    .take(id.0 - self.change_ticks.len() + 1),
+            );
         }
-        self.resources[id.0] = UnsafeCell::new(Some(Box::new(value)));
     }
 }
 
@@ -1,8 +1,12 @@
 //! Building of stage pipelines, which are used to organize system
 //! execution order while ensuring resource borrow safety.
 
-use crate::event::HandleStrategy;
+use crate::channel::EventChannelHooks;
+use crate::event::{HandleStrategy, UnhandledEventPolicy};
+use crate::filter::ArchetypeAccess;
 use crate::scheduler::OrExtend;
+#[cfg(feature = "serde")]
+use crate::EventRegistry;
 use crate::{
     resource_id_for_component, CachedEventHandler, CachedSystem, Event, EventHandler,
     RawEventHandler, RawSystem, ResourceId, Resources, Scheduler, System,
@@ -10,13 +14,38 @@ use crate::{
 use hashbrown::HashSet;
 use legion::storage::ComponentTypeId;
 
+/// The default maximum depth of event cascades. See `EventsBuilder::set_max_cascade_depth`.
+pub const DEFAULT_MAX_CASCADE_DEPTH: usize = 16;
+
 /// Builder of event pipelines.
-#[derive(Default)]
 pub struct EventsBuilder {
     /// Vector of end-of-dispatch event handlers.
     ///
     /// This vector is indexed by the `EventId`.
     end_of_dispatch: Vec<Vec<Box<dyn RawEventHandler>>>,
+    /// Maximum depth of event cascades.
+    max_cascade_depth: usize,
+    /// Whether batches of the same event type are coalesced.
+    coalesce_events: bool,
+    /// Policy for events which have no handlers or readers.
+    unhandled_event_policy: UnhandledEventPolicy,
+    /// Registry of event types recorded in the event journal,
+    /// or `None` if events are not recorded.
+    #[cfg(feature = "serde")]
+    event_registry: Option<EventRegistry>,
+}
+
+impl Default for EventsBuilder {
+    fn default() -> Self {
+        Self {
+            end_of_dispatch: vec![],
+            max_cascade_depth: DEFAULT_MAX_CASCADE_DEPTH,
+            coalesce_events: false,
+            unhandled_event_policy: UnhandledEventPolicy::default(),
+            #[cfg(feature = "serde")]
+            event_registry: None,
+        }
+    }
 }
 
 impl EventsBuilder {
@@ -31,7 +60,10 @@ impl EventsBuilder {
         H: EventHandler<E>,
         E: Event,
     {
-        self.add_boxed(Box::new(CachedEventHandler::new(handler, "null")))
+        self.add_boxed(Box::new(CachedEventHandler::new(
+            handler,
+            std::any::type_name::<H>(),
+        )))
     }
 
     /// Adds a boxed event handler.
@@ -39,6 +71,7 @@ impl EventsBuilder {
         assert_valid_deps(
             handler.resource_reads(),
             handler.resource_writes(),
+            handler.resource_concurrent_writes(),
             handler.name(),
         );
 
@@ -49,7 +82,14 @@ impl EventsBuilder {
             _ => unimplemented!("unimplemented handle strategy"),
         };
 
-        events_vec.get_mut_or_extend(event_id.0).push(handler);
+        // Keep handlers sorted by descending priority, inserting
+        // after any handlers with the same priority.
+        let handlers = events_vec.get_mut_or_extend(event_id.0);
+        let index = handlers
+            .iter()
+            .position(|other| other.priority() < handler.priority())
+            .unwrap_or_else(|| handlers.len());
+        handlers.insert(index, handler);
     }
 
     /// Adds an event handler to this builder, returning the `EventsBuilder`
@@ -63,11 +103,94 @@ impl EventsBuilder {
         self
     }
 
+    /// Sets the maximum depth of event cascades.
+    ///
+    /// Events triggered by an event handler are handled later during the
+    /// same dispatch, after the handlers of the triggering event have run.
+    /// Such events may in turn trigger further events, forming a cascade.
+    /// The depth of an event is the number of handlers in the chain which
+    /// led to it being triggered; events triggered by systems have a depth of 0.
+    ///
+    /// When an event would exceed the maximum depth, the scheduler stops
+    /// handling events for the rest of the dispatch rather than handling
+    /// events in an infinite loop. Once all running tasks have completed,
+    /// `Scheduler::try_execute` returns a `CascadeError` naming the chain
+    /// of events, and `Scheduler::execute` panics with it.
+    /// The default is `DEFAULT_MAX_CASCADE_DEPTH`.
+    pub fn set_max_cascade_depth(&mut self, depth: usize) {
+        self.max_cascade_depth = depth;
+    }
+
+    /// Sets the maximum depth of event cascades, returning the
+    /// `EventsBuilder` for method chaining. See `set_max_cascade_depth`.
+    pub fn with_max_cascade_depth(mut self, depth: usize) -> Self {
+        self.set_max_cascade_depth(depth);
+        self
+    }
+
+    /// Sets whether batches of events of the same type are coalesced
+    /// before being handled.
+    ///
+    /// When enabled, events triggered by different systems are collected
+    /// and handled as one batch once all running systems have completed,
+    /// so `EventHandler::handle_batch` observes all events of a type
+    /// triggered during a tick at once. Events triggered by event handlers
+    /// are coalesced in the same way after the running handlers complete.
+    ///
+    /// When disabled, each batch of events is handled separately as soon as
+    /// the handlers' resources are available.
+    ///
+    /// This is disabled by default.
+    pub fn set_coalesce_events(&mut self, coalesce: bool) {
+        self.coalesce_events = coalesce;
+    }
+
+    /// Sets whether batches of events of the same type are coalesced, returning
+    /// the `EventsBuilder` for method chaining. See `set_coalesce_events`.
+    pub fn with_coalesce_events(mut self, coalesce: bool) -> Self {
+        self.set_coalesce_events(coalesce);
+        self
+    }
+
+    /// Sets the policy for events which are triggered but have no
+    /// event handlers or `EventReader`s. See `UnhandledEventPolicy`.
+    pub fn set_unhandled_event_policy(&mut self, policy: UnhandledEventPolicy) {
+        self.unhandled_event_policy = policy;
+    }
+
+    /// Sets the policy for unhandled events, returning the `EventsBuilder`
+    /// for method chaining. See `set_unhandled_event_policy`.
+    pub fn with_unhandled_event_policy(mut self, policy: UnhandledEventPolicy) -> Self {
+        self.set_unhandled_event_policy(policy);
+        self
+    }
+
+    /// Enables recording of triggered events in an `EventJournal`.
+    ///
+    /// Every batch of events whose type is registered in `registry` is
+    /// recorded along with the tick and the triggering system, including
+    /// events triggered through the `Scheduler`. The journal can be
+    /// accessed through `Scheduler::journal` and replayed into a fresh
+    /// scheduler using `Scheduler::replay`.
+    #[cfg(feature = "serde")]
+    pub fn set_event_journal(&mut self, registry: EventRegistry) {
+        self.event_registry = Some(registry);
+    }
+
+    /// Enables recording of triggered events, returning the `EventsBuilder`
+    /// for method chaining. See `set_event_journal`.
+    #[cfg(feature = "serde")]
+    pub fn with_event_journal(mut self, registry: EventRegistry) -> Self {
+        self.set_event_journal(registry);
+        self
+    }
+
     /// Finishes construction of this events builder, returning a `SchedulerBuilder`
     /// which can be used to further add systems.
     pub fn finish(self) -> SchedulerBuilder {
         SchedulerBuilder {
             stages: vec![],
+            archetype_conflicts: false,
             events: self,
         }
     }
@@ -86,6 +209,8 @@ pub struct SchedulerBuilder {
     /// Stages which have been created so far. New systems can
     /// be inserted into existing stages or be added in a new stage.
     stages: Vec<Stage>,
+    /// Whether component conflicts are detected per archetype.
+    archetype_conflicts: bool,
     events: EventsBuilder,
 }
 
@@ -99,18 +224,57 @@ impl SchedulerBuilder {
         Self::default()
     }
 
+    /// Sets whether component conflicts between systems are
+    /// detected per archetype rather than per component type.
+    ///
+    /// When enabled, two systems accessing the same component
+    /// may run in parallel if their queries' filters prove that
+    /// they never match the same entities, e.g. a query with
+    /// `With<Player>` and one with `Without<Player>`. Filters which
+    /// only restrict the matched archetypes, such as `With` and `Tagged`,
+    /// are not enough by themselves, since an entity may have both
+    /// `Player` and `Projectile`.
+    ///
+    /// Random component access through `PreparedWorld` is then only
+    /// granted for components declared without archetype restrictions,
+    /// e.g. through `ReadComponent`, `WriteComponent` or `QueryAccessor`.
+    ///
+    /// This is disabled by default. It must be set before any systems are added.
+    ///
+    /// # Panics
+    /// Panics if systems have already been added.
+    pub fn set_archetype_conflicts(&mut self, enabled: bool) {
+        assert!(
+            self.stages.is_empty(),
+            "archetype conflicts must be set before systems are added"
+        );
+        self.archetype_conflicts = enabled;
+    }
+
+    /// Sets whether component conflicts are detected per archetype, returning
+    /// the `SchedulerBuilder` for method chaining. See `set_archetype_conflicts`.
+    pub fn with_archetype_conflicts(mut self, enabled: bool) -> Self {
+        self.set_archetype_conflicts(enabled);
+        self
+    }
+
     /// Adds a boxed system to the stage pipeline.
-    pub fn add_boxed(&mut self, system: Box<dyn RawSystem>) {
+    pub fn add_boxed(&mut self, mut system: Box<dyn RawSystem>) {
         assert_valid_deps(
             system.resource_reads(),
             system.resource_writes(),
+            system.resource_concurrent_writes(),
             system.name(),
         );
 
+        let archetype_conflicts = self.archetype_conflicts;
+        if archetype_conflicts {
+            system.restrict_random_access();
+        }
         if let Some(stage) = self
             .stages
             .iter_mut()
-            .find(|stage| !stage.conflicts_with(&*system))
+            .find(|stage| !stage.conflicts_with(&*system, archetype_conflicts))
         {
             stage.add(system);
         } else {
@@ -123,7 +287,7 @@ impl SchedulerBuilder {
 
     /// Adds a system to the stage pipeline.
     pub fn add<S: System + 'static>(&mut self, system: S) {
-        let system = CachedSystem::new(system, "null");
+        let system = CachedSystem::new(system, std::any::type_name::<S>());
 
         self.add_boxed(Box::new(system));
     }
@@ -141,9 +305,20 @@ impl SchedulerBuilder {
         let mut systems = vec![];
         let mut reads = vec![];
         let mut writes = vec![];
+        let mut concurrent_writes = vec![];
+        let mut event_channels: Vec<Option<EventChannelHooks>> = vec![];
+
+        let handlers = self.events.end_of_dispatch.iter().flatten();
+        for hooks in handlers.flat_map(|handler| handler.event_channels()) {
+            event_channels.set_or_extend(hooks.event_id.0, Some(*hooks));
+        }
 
         for stage in self.stages {
             for system in &stage.systems {
+                for hooks in system.event_channels() {
+                    event_channels.set_or_extend(hooks.event_id.0, Some(*hooks));
+                }
+
                 let mut system_reads = vec![];
                 let mut system_writes = vec![];
 
@@ -166,19 +341,39 @@ impl SchedulerBuilder {
 
                 reads.push(system_reads);
                 writes.push(system_writes);
+                concurrent_writes.push(system.resource_concurrent_writes().to_vec());
             }
 
             systems.push(stage.systems);
         }
 
+        let max_cascade_depth = self.events.max_cascade_depth;
+        let coalesce_events = self.events.coalesce_events;
+        let unhandled_event_policy = self.events.unhandled_event_policy;
+        #[cfg(feature = "serde")]
+        let event_registry = self.events.event_registry;
+        let end_of_dispatch = self
+            .events
+            .end_of_dispatch
+            .into_iter()
+            .map(split_handler_stages)
+            .collect();
+
         // Safety: the builder must work correctly to ensure
         // that stages are correct.
         unsafe {
             Scheduler::new(
                 systems,
-                self.events.end_of_dispatch,
+                end_of_dispatch,
                 reads,
                 writes,
+                concurrent_writes,
+                event_channels,
+                max_cascade_depth,
+                coalesce_events,
+                unhandled_event_policy,
+                #[cfg(feature = "serde")]
+                event_registry,
                 resources,
             )
         }
@@ -193,6 +388,10 @@ struct Stage {
     reads: HashSet<Access>,
     /// Set of resources which are written by this stage.
     writes: HashSet<Access>,
+    /// Set of resources which are concurrently written by this stage.
+    concurrent_writes: HashSet<Access>,
+    /// Archetype-level component accesses of the systems in this stage.
+    archetype_accesses: Vec<ArchetypeAccess>,
 }
 
 impl Default for Stage {
@@ -201,6 +400,8 @@ impl Default for Stage {
             systems: vec![],
             reads: HashSet::new(),
             writes: HashSet::new(),
+            concurrent_writes: HashSet::new(),
+            archetype_accesses: vec![],
         }
     }
 }
@@ -212,27 +413,58 @@ impl Stage {
     }
 
     /// Returns whether the given system conflicts with this stage.
-    pub fn conflicts_with(&self, system: &dyn RawSystem) -> bool {
-        system
-            .resource_reads()
+    ///
+    /// Concurrent writes only conflict with reads and writes,
+    /// not with other concurrent writes. If `archetype_conflicts`
+    /// is set, component accesses only conflict if they may
+    /// match the same archetypes.
+    pub fn conflicts_with(&self, system: &dyn RawSystem, archetype_conflicts: bool) -> bool {
+        system.resource_reads().iter().copied().any(|resource| {
+            self.writes.contains(&Access::Resource(resource))
+                || self.concurrent_writes.contains(&Access::Resource(resource))
+        }) || system.resource_writes().iter().copied().any(|resource| {
+            self.reads.contains(&Access::Resource(resource))
+                || self.writes.contains(&Access::Resource(resource))
+                || self.concurrent_writes.contains(&Access::Resource(resource))
+        }) || system
+            .resource_concurrent_writes()
             .iter()
             .copied()
-            .any(|resource| self.writes.contains(&Access::Resource(resource)))
-            || system.resource_writes().iter().copied().any(|resource| {
+            .any(|resource| {
                 self.reads.contains(&Access::Resource(resource))
                     || self.writes.contains(&Access::Resource(resource))
             })
-            || system
-                .component_reads()
-                .iter()
-                .copied()
-                .any(|component| self.writes.contains(&Access::Component(component)))
+            || if archetype_conflicts {
+                self.archetype_conflicts_with(system)
+            } else {
+                self.component_conflicts_with(system)
+            }
+    }
+
+    /// Returns whether the given system's component accesses
+    /// conflict with this stage per component type.
+    fn component_conflicts_with(&self, system: &dyn RawSystem) -> bool {
+        system
+            .component_reads()
+            .iter()
+            .copied()
+            .any(|component| self.writes.contains(&Access::Component(component)))
             || system.component_writes().iter().copied().any(|component| {
                 self.reads.contains(&Access::Component(component))
                     || self.writes.contains(&Access::Component(component))
             })
     }
 
+    /// Returns whether the given system's component accesses
+    /// conflict with this stage per archetype.
+    fn archetype_conflicts_with(&self, system: &dyn RawSystem) -> bool {
+        system.archetype_accesses().iter().any(|access| {
+            self.archetype_accesses
+                .iter()
+                .any(|other| access.conflicts_with(other))
+        })
+    }
+
     /// Adds a system to this stage.
     pub fn add(&mut self, system: Box<dyn RawSystem>) {
         system
@@ -249,6 +481,13 @@ impl Stage {
             .for_each(|resource| {
                 self.writes.insert(Access::Resource(resource));
             });
+        system
+            .resource_concurrent_writes()
+            .iter()
+            .copied()
+            .for_each(|resource| {
+                self.concurrent_writes.insert(Access::Resource(resource));
+            });
         system
             .component_reads()
             .iter()
@@ -263,11 +502,87 @@ impl Stage {
             .for_each(|component| {
                 self.writes.insert(Access::Component(component));
             });
+        self.archetype_accesses.extend(system.archetype_accesses());
         self.systems.push(system);
     }
 }
 
-fn assert_valid_deps(reads: &[ResourceId], writes: &[ResourceId], name: &str) {
+/// A sub-stage of an event handler pipeline. All handlers
+/// in a sub-stage are run in parallel on the same events.
+#[derive(Default)]
+struct HandlerStage {
+    handlers: Vec<Box<dyn RawEventHandler>>,
+    reads: HashSet<ResourceId>,
+    writes: HashSet<ResourceId>,
+    concurrent_writes: HashSet<ResourceId>,
+}
+
+impl HandlerStage {
+    /// Returns whether the given handler can run in parallel
+    /// with the handlers in this sub-stage.
+    ///
+    /// Handlers which may stop propagation are always placed in
+    /// their own sub-stage, since handlers with a lower priority
+    /// must observe which events they stopped.
+    fn can_add(&self, handler: &dyn RawEventHandler) -> bool {
+        if handler.stops_propagation()
+            || self.handlers.iter().any(|other| other.stops_propagation())
+        {
+            return false;
+        }
+
+        !(handler.resource_reads().iter().any(|resource| {
+            self.writes.contains(resource) || self.concurrent_writes.contains(resource)
+        }) || handler.resource_writes().iter().any(|resource| {
+            self.reads.contains(resource)
+                || self.writes.contains(resource)
+                || self.concurrent_writes.contains(resource)
+        }) || handler
+            .resource_concurrent_writes()
+            .iter()
+            .any(|resource| self.reads.contains(resource) || self.writes.contains(resource)))
+    }
+
+    fn add(&mut self, handler: Box<dyn RawEventHandler>) {
+        self.reads.extend(handler.resource_reads().iter().copied());
+        self.writes
+            .extend(handler.resource_writes().iter().copied());
+        self.concurrent_writes
+            .extend(handler.resource_concurrent_writes().iter().copied());
+        self.handlers.push(handler);
+    }
+}
+
+/// Splits the handlers of an event, sorted by priority, into sub-stages
+/// of handlers which can run in parallel.
+///
+/// Only consecutive handlers are grouped, so a handler never runs
+/// before a conflicting handler with a higher priority.
+fn split_handler_stages(
+    handlers: Vec<Box<dyn RawEventHandler>>,
+) -> Vec<Vec<Box<dyn RawEventHandler>>> {
+    let mut stages: Vec<HandlerStage> = vec![];
+
+    for handler in handlers {
+        match stages.last_mut() {
+            Some(stage) if stage.can_add(&*handler) => stage.add(handler),
+            _ => {
+                let mut stage = HandlerStage::default();
+                stage.add(handler);
+                stages.push(stage);
+            }
+        }
+    }
+
+    stages.into_iter().map(|stage| stage.handlers).collect()
+}
+
+fn assert_valid_deps(
+    reads: &[ResourceId],
+    writes: &[ResourceId],
+    concurrent_writes: &[ResourceId],
+    name: &str,
+) {
     // Verify that there are no conflicts in the system's own resource access.
     // This prevents UB such as mutable aliasing.
     assert!(
@@ -275,6 +590,13 @@ fn assert_valid_deps(reads: &[ResourceId], writes: &[ResourceId], name: &str) {
         "system {} cannot read and write same resource",
         name
     );
+    assert!(
+        concurrent_writes
+            .iter()
+            .all(|resource| !reads.contains(resource) && !writes.contains(resource)),
+        "system {} cannot access a resource both concurrently and exclusively",
+        name
+    );
     let valid_mutable = writes.iter().all(|resource| {
         !reads.contains(resource) && writes.iter().filter(|res| *res == resource).count() == 1
     });
@@ -284,3 +606,236 @@ fn assert_valid_deps(reads: &[ResourceId], writes: &[ResourceId], name: &str) {
         name
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Changed, Concurrent, ConcurrentResource, PreparedWorld, Query, QueryAccessor, Read,
+        SystemData, TryRead, With, Without, Write, WriteComponent,
+    };
+    use legion::query::{Read as ReadView, Write as WriteView};
+
+    struct Ev;
+
+    struct Timer;
+    struct First;
+    struct Second;
+
+    struct WriteFirst;
+
+    impl System for WriteFirst {
+        type SystemData = Write<Timer, First>;
+
+        fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {}
+    }
+
+    struct WriteSecond;
+
+    impl System for WriteSecond {
+        type SystemData = Write<Timer, Second>;
+
+        fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {}
+    }
+
+    struct Counter;
+
+    impl ConcurrentResource for Counter {}
+
+    struct Increment;
+
+    impl System for Increment {
+        type SystemData = Concurrent<Counter>;
+
+        fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {}
+    }
+
+    struct ReadCounter;
+
+    impl System for ReadCounter {
+        type SystemData = Read<Counter>;
+
+        fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {}
+    }
+
+    struct WriteCounter;
+
+    impl System for WriteCounter {
+        type SystemData = Write<Counter>;
+
+        fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {}
+    }
+
+    struct HandleFirst;
+
+    impl EventHandler<Ev> for HandleFirst {
+        type HandlerData = Write<Timer, First>;
+
+        fn handle(&mut self, _event: &Ev, _data: &mut <Self::HandlerData as SystemData>::Output) {}
+    }
+
+    struct HandleSecond;
+
+    impl EventHandler<Ev> for HandleSecond {
+        type HandlerData = Write<Timer, Second>;
+
+        fn handle(&mut self, _event: &Ev, _data: &mut <Self::HandlerData as SystemData>::Output) {}
+    }
+
+    struct Stopper;
+
+    impl EventHandler<Ev> for Stopper {
+        type HandlerData = ();
+
+        fn handle(&mut self, _event: &Ev, _data: &mut <Self::HandlerData as SystemData>::Output) {}
+
+        fn stops_propagation(&self) -> bool {
+            true
+        }
+    }
+
+    fn handler_stages(builder: EventsBuilder) -> Vec<usize> {
+        let id = crate::event::event_id_for::<Ev>();
+        split_handler_stages(builder.end_of_dispatch.into_iter().nth(id.0).unwrap())
+            .iter()
+            .map(Vec::len)
+            .collect()
+    }
+
+    #[test]
+    fn handler_stages_split_on_conflicts() {
+        let builder = EventsBuilder::new().with(HandleFirst).with(HandleSecond);
+        assert_eq!(handler_stages(builder), vec![2]);
+
+        let builder = EventsBuilder::new()
+            .with(HandleFirst)
+            .with(HandleSecond)
+            .with(HandleFirst);
+        assert_eq!(handler_stages(builder), vec![2, 1]);
+
+        let builder = EventsBuilder::new()
+            .with(HandleFirst)
+            .with(Stopper)
+            .with(HandleSecond);
+        assert_eq!(handler_stages(builder), vec![1, 1, 1]);
+    }
+
+    #[test]
+    fn concurrent_writes() {
+        let builder = SchedulerBuilder::new().with(Increment).with(Increment);
+        assert_eq!(builder.stages.len(), 1);
+
+        let builder = SchedulerBuilder::new().with(Increment).with(ReadCounter);
+        assert_eq!(builder.stages.len(), 2);
+
+        let builder = SchedulerBuilder::new().with(WriteCounter).with(Increment);
+        assert_eq!(builder.stages.len(), 2);
+    }
+
+    #[test]
+    fn keyed_resources_do_not_conflict() {
+        let builder = SchedulerBuilder::new().with(WriteFirst).with(WriteSecond);
+        assert_eq!(builder.stages.len(), 1);
+
+        let builder = SchedulerBuilder::new().with(WriteFirst).with(WriteFirst);
+        assert_eq!(builder.stages.len(), 2);
+    }
+
+    struct Age;
+    struct Height;
+    struct Position;
+    struct Velocity;
+    struct Player;
+    struct Projectile;
+    struct Static;
+
+    /// Defines a system which does nothing with the given system data.
+    macro_rules! noop_system {
+        ($name:ident, $data:ty) => {
+            struct $name;
+
+            impl System for $name {
+                type SystemData = $data;
+
+                fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {}
+            }
+        };
+    }
+
+    noop_system!(AccessorReadAge, QueryAccessor<ReadView<Age>>);
+    noop_system!(AccessorWriteAge, QueryAccessor<WriteView<Age>>);
+    noop_system!(AccessorWriteHeight, QueryAccessor<WriteView<Height>>);
+    noop_system!(
+        ReadChangedPosition,
+        Query<ReadView<Velocity>, Changed<Position>>
+    );
+    noop_system!(WritePosition, Query<WriteView<Position>>);
+    noop_system!(ReadDynamic, Query<ReadView<Position>, Without<Static>>);
+    noop_system!(WriteStatic, Query<WriteView<Static>>);
+    noop_system!(TryReadAge, Query<(ReadView<Position>, TryRead<Age>)>);
+    noop_system!(WriteAge, Query<WriteView<Age>>);
+    noop_system!(MovePlayers, Query<WriteView<Position>, With<Player>>);
+    noop_system!(MoveOthers, Query<WriteView<Position>, Without<Player>>);
+    noop_system!(
+        MoveProjectiles,
+        Query<(WriteView<Position>, ReadView<Projectile>)>
+    );
+    noop_system!(Teleport, (WriteComponent<Position>, PreparedWorld));
+
+    #[test]
+    fn conflicting_accessors() {
+        let builder = SchedulerBuilder::new()
+            .with(AccessorReadAge)
+            .with(AccessorWriteAge);
+        assert_eq!(builder.stages.len(), 2);
+
+        let builder = SchedulerBuilder::new()
+            .with(AccessorReadAge)
+            .with(AccessorWriteHeight);
+        assert_eq!(builder.stages.len(), 1);
+    }
+
+    #[test]
+    fn query_filters() {
+        // `Changed` reads the filtered component.
+        let builder = SchedulerBuilder::new()
+            .with(ReadChangedPosition)
+            .with(WritePosition);
+        assert_eq!(builder.stages.len(), 2);
+
+        // Archetype filters do not access components.
+        let builder = SchedulerBuilder::new().with(ReadDynamic).with(WriteStatic);
+        assert_eq!(builder.stages.len(), 1);
+
+        // `TryRead` reads the component if it exists.
+        let builder = SchedulerBuilder::new().with(TryReadAge).with(WriteAge);
+        assert_eq!(builder.stages.len(), 2);
+    }
+
+    #[test]
+    fn archetype_conflicts() {
+        let builder = SchedulerBuilder::new().with(MovePlayers).with(MoveOthers);
+        assert_eq!(builder.stages.len(), 2);
+
+        let builder = SchedulerBuilder::new()
+            .with_archetype_conflicts(true)
+            .with(MovePlayers)
+            .with(MoveOthers);
+        assert_eq!(builder.stages.len(), 1);
+
+        // An entity may be both a player and a projectile.
+        let builder = SchedulerBuilder::new()
+            .with_archetype_conflicts(true)
+            .with(MovePlayers)
+            .with(MoveProjectiles);
+        assert_eq!(builder.stages.len(), 2);
+
+        // Random access through `PreparedWorld` is not restricted to archetypes.
+        let builder = SchedulerBuilder::new()
+            .with_archetype_conflicts(true)
+            .with(MovePlayers)
+            .with(MoveOthers)
+            .with(Teleport);
+        assert_eq!(builder.stages.len(), 2);
+    }
+}
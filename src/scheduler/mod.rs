@@ -3,21 +3,33 @@ use bumpalo::Bump;
 use crossbeam::{Receiver, Sender};
 use rayon::prelude::*;
 use smallvec::{smallvec, SmallVec};
-use std::collections::VecDeque;
+use std::collections::{BinaryHeap, VecDeque};
 use thread_local::ThreadLocal;
 
 mod builder;
 
-use crate::event::event_id_for;
+use crate::channel::EventChannelHooks;
+use crate::dynamic::{alloc_boxed, alloc_bytes};
+use crate::event::{
+    drop_events, event_id_for, event_layout, event_name, ScheduledBatch, ScheduledTime,
+    UnhandledEventPolicy, UnhandledEvents,
+};
 use crate::system::SystemCtx;
 use crate::{
-    resources::RESOURCE_ID_MAPPINGS, system::SYSTEM_ID_MAPPINGS, Event, EventId, RawEventHandler,
-    RawSystem, ResourceId, Resources, SystemId,
+    resources::RESOURCE_ID_MAPPINGS, system::SYSTEM_ID_MAPPINGS, BoxedEvent, Event, EventId,
+    RawEventHandler, RawSystem, ResourceId, Resources, SystemId,
 };
-pub use builder::{EventsBuilder, SchedulerBuilder};
+#[cfg(feature = "serde")]
+use crate::{EventJournal, EventRegistry};
+pub use builder::{EventsBuilder, SchedulerBuilder, DEFAULT_MAX_CASCADE_DEPTH};
 use legion::world::World;
+use std::alloc::Layout;
+use std::cell::UnsafeCell;
 use std::iter;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{cmp, fmt, mem, ptr};
 
 /// Context of a running system, used for internal purposes.
 #[derive(Clone)]
@@ -52,6 +64,40 @@ unsafe impl<T: ?Sized + Sync> Sync for SharedRawPtr<T> {}
 
 type DynSystem = (dyn RawSystem + 'static);
 
+/// Thread-local bump allocators used to allocate events. These are
+/// shared by the scheduler and the contexts of all systems.
+///
+/// Allocators are only reset by the scheduler between dispatches,
+/// through `reset()`, while no system or event handler is running.
+pub(crate) struct BumpArenas(UnsafeCell<ThreadLocal<Bump>>);
+
+// Safety: allocators are only accessed from their own thread,
+// except in `reset()`, which requires exclusive access.
+unsafe impl Sync for BumpArenas {}
+
+impl BumpArenas {
+    fn new() -> Self {
+        Self(UnsafeCell::new(ThreadLocal::new()))
+    }
+
+    /// Returns the bump allocator of the current thread.
+    pub(crate) fn get(&self) -> &Bump {
+        // Safety: `reset()` is never called while allocators are borrowed.
+        unsafe { (*self.0.get()).get_or_default() }
+    }
+
+    /// Resets all allocators, freeing their allocations.
+    ///
+    /// # Safety
+    /// No allocators may be accessed by other threads during
+    /// this call, and no allocations may be accessed afterwards.
+    unsafe fn reset(&self) {
+        for bump in (*self.0.get()).iter_mut() {
+            bump.reset();
+        }
+    }
+}
+
 /// A mutable raw pointer to some `T`.
 ///
 /// # Safety
@@ -80,8 +126,9 @@ pub(crate) enum TaskMessage {
     SystemComplete(SystemId),
     /// Indicates that all systems in a stage have completed.
     StageComplete(StageId),
-    /// Indicates that an event handler pipeline has finished running.
-    EventHandlingComplete(EventId),
+    /// Indicates that a sub-stage of an event handler pipeline
+    /// has finished running on a batch of events.
+    EventHandlingComplete(EventBatch, usize),
     /// Requests that one or more events be handled.
     ///
     /// `EndOfSystem` handlers should be run after this message is sent.
@@ -92,23 +139,152 @@ pub(crate) enum TaskMessage {
     /// type, allocated in one of the thread-local bump allocators.
     TriggerEvents {
         id: EventId,
+        /// The system or event handler which triggered the events.
+        source: SystemId,
         ptr: *const (),
         len: usize,
     },
+    /// Requests that events be handled during a later dispatch.
+    ScheduleEvents {
+        id: EventId,
+        /// The system or event handler which scheduled the events.
+        source: SystemId,
+        time: ScheduledTime,
+        events: Box<dyn ScheduledBatch>,
+    },
 }
 
 unsafe impl Send for TaskMessage {}
 unsafe impl Sync for TaskMessage {}
 
+/// A batch of triggered events waiting to be moved into their event channel,
+/// or to be dropped at the end of a dispatch.
+#[derive(Debug, Clone, Copy)]
+struct PendingEvents {
+    id: EventId,
+    ptr: *const (),
+    len: usize,
+}
+
+// Safety: see `Task`.
+unsafe impl Send for PendingEvents {}
+unsafe impl Sync for PendingEvents {}
+
+/// A batch of events being handled by an event handler pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct EventBatch {
+    id: EventId,
+    ptr: *const (),
+    len: usize,
+    /// Propagation flags for each event, allocated in the bump
+    /// allocator and shared by all sub-stages of the pipeline.
+    stopped: *const AtomicBool,
+    /// Index into `Scheduler::cascades` of the link
+    /// describing how these events were triggered.
+    cascade: usize,
+}
+
+// Safety: see `Task`.
+unsafe impl Send for EventBatch {}
+unsafe impl Sync for EventBatch {}
+
+/// A batch of triggered events waiting to be coalesced
+/// with other batches of the same event type.
+#[derive(Debug, Clone, Copy)]
+struct QueuedEvents {
+    id: EventId,
+    /// See `Scheduler::create_event_batch`.
+    parent: Option<usize>,
+    ptr: *const (),
+    len: usize,
+}
+
+// Safety: see `Task`.
+unsafe impl Send for QueuedEvents {}
+unsafe impl Sync for QueuedEvents {}
+
+/// A batch of events scheduled to be handled during a later dispatch,
+/// ordered so that `BinaryHeap` yields the earliest batch first.
+///
+/// `K` is the type of the time at which the events are handled.
+struct ScheduledEvents<K> {
+    at: K,
+    /// Sequence number used to handle batches scheduled
+    /// for the same time in the order they were scheduled.
+    seq: u64,
+    id: EventId,
+    /// The system or event handler which scheduled the events.
+    source: Option<SystemId>,
+    events: Box<dyn ScheduledBatch>,
+}
+
+impl<K: Ord> PartialEq for ScheduledEvents<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == cmp::Ordering::Equal
+    }
+}
+
+impl<K: Ord> Eq for ScheduledEvents<K> {}
+
+impl<K: Ord> PartialOrd for ScheduledEvents<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord> Ord for ScheduledEvents<K> {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        // Reversed, since `BinaryHeap` is a max-heap.
+        (&other.at, other.seq).cmp(&(&self.at, self.seq))
+    }
+}
+
+/// Error returned by `Scheduler::try_execute` when a chain of events
+/// triggered by event handlers exceeds the maximum cascade depth.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CascadeError {
+    /// The maximum cascade depth of the scheduler.
+    pub max_depth: usize,
+    /// Names of the event types in the cascade, starting
+    /// with the first events which were triggered.
+    pub chain: Vec<&'static str>,
+}
+
+impl fmt::Display for CascadeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "event cascade exceeded the maximum depth of {}: {}",
+            self.max_depth,
+            self.chain.join(" -> ")
+        )
+    }
+}
+
+impl std::error::Error for CascadeError {}
+
+/// A link in a chain of events triggered by event handlers.
+#[derive(Debug, Clone, Copy)]
+struct CascadeLink {
+    event: EventId,
+    /// Index into `Scheduler::cascades` of the link for the events
+    /// whose handlers triggered these events, or `None` if they
+    /// were triggered by a system or `Scheduler::trigger`.
+    parent: Option<usize>,
+    /// Number of links before this one.
+    depth: usize,
+}
+
 /// A task to run. This can either be a stage (mutliple systems run in parallel),
-/// a oneshot system, or an event handling pipeline.
+/// a oneshot system, or a sub-stage of an event handling pipeline.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[allow(dead_code)]
 enum Task {
     Stage(StageId),
     Oneshot(SystemId),
-    HandleEvent(EventId, *const (), usize),
-    // TODO: event pipeline
+    /// Runs the sub-stage with the given index on a batch of events.
+    /// Once it completes, the next sub-stage is scheduled.
+    HandleEvent(EventBatch, usize),
 }
 
 // Safety: *const [()] is allocated in the bump allocator,
@@ -163,12 +339,19 @@ pub struct Scheduler {
     ///
     /// This vector is indexed by the `ResourceId`.
     reads_held: Vec<u32>,
+    /// Vector of reference counts representing the number of tasks currently
+    /// holding concurrent write access to a resource.
+    ///
+    /// This vector is indexed by the `ResourceId`.
+    concurrent_writes_held: Vec<u32>,
 
-    /// Thread-local bump allocator used to allocate events.
+    /// Thread-local bump allocators used to allocate events.
+    ///
+    /// All allocators are reset at the end of `execute()`.
     ///
     /// TODO: implement a lock-free bump arena instead.
     #[derivative(Debug = "ignore")]
-    bump: Arc<ThreadLocal<Bump>>,
+    bump: Arc<BumpArenas>,
 
     /// Number of currently running systems.
     runnning_systems_count: usize,
@@ -194,6 +377,10 @@ pub struct Scheduler {
     ///
     /// This vector is indexed by the `SystemId`.
     system_writes: Vec<ResourceVec>,
+    /// Vector containing the concurrent writes required for each system.
+    ///
+    /// This vector is indexed by the `SystemId`.
+    system_concurrent_writes: Vec<ResourceVec>,
     /// Vector containing the reads required for each stage.
     ///
     /// This vector is indexed by the `StageId`.
@@ -202,6 +389,10 @@ pub struct Scheduler {
     ///
     /// This vector is indexed by the `StageId`.
     stage_writes: Vec<ResourceVec>,
+    /// Vector containing the concurrent writes required for each stage.
+    ///
+    /// This vector is indexed by the `StageId`.
+    stage_concurrent_writes: Vec<ResourceVec>,
 
     // === Event handling ===
     /// Vector containing event handlers. This vector is indexed by the `SystemID`.
@@ -211,22 +402,101 @@ pub struct Scheduler {
     #[derivative(Debug = "ignore")]
     event_handlers: Vec<Option<Box<dyn RawEventHandler>>>,
 
-    /// Vector containing the event handler system IDs required for handling
-    /// each given event. All handlers in this vector have `EndOfTick` handle
-    /// strategies; `EndOfSystem` is handled separately.
+    /// Vector containing the sub-stages of each event handler pipeline.
+    /// Handlers in a sub-stage are run in parallel; sub-stages are run in order.
+    /// All handlers in this vector have `EndOfTick` handle strategies;
+    /// `EndOfSystem` is handled separately.
     ///
-    /// This vector is indexed by the `EventId`.
-    end_of_tick_handlers: Vec<SmallVec<[SystemId; 4]>>,
+    /// This vector is indexed by the `EventId`, then by the index of the sub-stage.
+    event_stages: Vec<Vec<SmallVec<[SystemId; 4]>>>,
 
-    /// Vector containing the reads required for each event handler __pipeline__.
+    /// Vector containing the reads required for each event handler sub-stage.
     ///
-    /// This vector is indexed by the `EventId`.
-    event_reads: Vec<ResourceVec>,
+    /// This vector is indexed by the `EventId`, then by the index of the sub-stage.
+    event_reads: Vec<Vec<ResourceVec>>,
+
+    /// Vector containing the writes required for each event handler sub-stage.
+    ///
+    /// This vector is indexed by the `EventId`, then by the index of the sub-stage.
+    event_writes: Vec<Vec<ResourceVec>>,
+
+    /// Vector containing the concurrent writes required for each event handler sub-stage.
+    ///
+    /// This vector is indexed by the `EventId`, then by the index of the sub-stage.
+    event_concurrent_writes: Vec<Vec<ResourceVec>>,
 
-    /// Vector containing the writes required for each event handler __pipeline__.
+    /// Vector containing the hooks for the event channel of each event
+    /// type read by an `EventReader`, or `None` if no reader exists.
     ///
     /// This vector is indexed by the `EventId`.
-    event_writes: Vec<ResourceVec>,
+    #[derivative(Debug = "ignore")]
+    event_channels: Vec<Option<EventChannelHooks>>,
+    /// Batches of events which have been triggered but not yet moved
+    /// into their event channel because a reader of the channel was running.
+    pending_events: Vec<PendingEvents>,
+    /// Batches of events which are owned by the scheduler, i.e. which have
+    /// not been moved into an event channel. These are dropped at the
+    /// end of `execute()`.
+    owned_events: Vec<PendingEvents>,
+
+    /// Links of all event cascades in the current dispatch. Cleared
+    /// at the end of `execute()`.
+    cascades: Vec<CascadeLink>,
+    /// Vector containing the index into `cascades` of the batch each
+    /// event handler is currently handling, or `None` if it is not running.
+    ///
+    /// This vector is indexed by the `SystemId`.
+    handled_cascades: Vec<Option<usize>>,
+    /// Maximum number of times events may trigger further
+    /// events through their handlers within a dispatch.
+    max_cascade_depth: usize,
+    /// Error for a cascade which exceeded the maximum depth during the
+    /// current dispatch. Once set, no further batches of events are
+    /// dispatched, and the error is returned at the end of `try_execute()`.
+    cascade_error: Option<CascadeError>,
+
+    /// Whether batches of the same event type are coalesced before being handled.
+    coalesce_events: bool,
+    /// Batches of events waiting to be coalesced. These are handled
+    /// once no tasks are running or waiting to run.
+    queued_events: Vec<QueuedEvents>,
+
+    /// Policy for events which have no handlers or readers.
+    unhandled_event_policy: UnhandledEventPolicy,
+    /// Numbers of unhandled events of each type to be recorded in
+    /// the `UnhandledEvents` resource at the end of `execute()`, or
+    /// to be reported by a panic then with `UnhandledEventPolicy::Panic`.
+    unhandled_events: Vec<(EventId, usize)>,
+
+    /// Number of dispatches which have completed. This is
+    /// the tick of the current or next dispatch.
+    tick: Arc<AtomicU64>,
+    /// Events scheduled to be handled during the dispatch with a given tick.
+    #[derivative(Debug = "ignore")]
+    scheduled_events: BinaryHeap<ScheduledEvents<u64>>,
+    /// Events scheduled to be handled during the first
+    /// dispatch which starts once a given instant has passed.
+    #[derivative(Debug = "ignore")]
+    timed_events: BinaryHeap<ScheduledEvents<Instant>>,
+    /// Number of batches of events which have been scheduled,
+    /// used to allocate sequence numbers.
+    scheduled_count: u64,
+
+    /// Registry of event types recorded in `journal`,
+    /// or `None` if events are not recorded.
+    #[cfg(feature = "serde")]
+    #[derivative(Debug = "ignore")]
+    event_registry: Option<EventRegistry>,
+    /// Journal of recorded events.
+    #[cfg(feature = "serde")]
+    #[derivative(Debug = "ignore")]
+    journal: EventJournal,
+    /// Names of systems and event handlers, recorded as the sources of
+    /// events in `journal` since `SystemId`s differ between runs.
+    ///
+    /// This vector is indexed by the `SystemId`.
+    #[cfg(feature = "serde")]
+    system_names: Vec<Option<&'static str>>,
 
     /// Receiving end of the channel used to communicate with running systems.
     #[derivative(Debug = "ignore")]
@@ -244,15 +514,25 @@ impl Scheduler {
     /// `deps` is a vector indexed by the system ID containing
     /// resources for each system.
     ///
+    /// `end_of_dispatch_handlers` is indexed by the `EventId`
+    /// and contains the sub-stages of each event handler pipeline.
+    ///
     /// # Safety
     /// The stages are assumed to have been assembled correctly:
-    /// no two systems in a stage may conflict with each other.
+    /// no two systems in a stage, and no two handlers in a
+    /// sub-stage, may conflict with each other.
     unsafe fn new(
         stages: Vec<Vec<Box<DynSystem>>>,
-        end_of_dispatch_handlers: Vec<Vec<Box<dyn RawEventHandler>>>,
+        end_of_dispatch_handlers: Vec<Vec<Vec<Box<dyn RawEventHandler>>>>,
         read_deps: Vec<Vec<ResourceId>>,
         write_deps: Vec<Vec<ResourceId>>,
-        resources: Resources,
+        concurrent_write_deps: Vec<Vec<ResourceId>>,
+        event_channels: Vec<Option<EventChannelHooks>>,
+        max_cascade_depth: usize,
+        coalesce_events: bool,
+        unhandled_event_policy: UnhandledEventPolicy,
+        #[cfg(feature = "serde")] event_registry: Option<EventRegistry>,
+        mut resources: Resources,
     ) -> Self {
         // Detect resources used by systems and create those vectors.
         // Also collect systems into uniform vector.
@@ -263,23 +543,38 @@ impl Scheduler {
         let mut system_writes: Vec<ResourceVec> = iter::repeat_with(|| smallvec![])
             .take(num_systems)
             .collect();
+        let mut system_concurrent_writes: Vec<ResourceVec> = iter::repeat_with(|| smallvec![])
+            .take(num_systems)
+            .collect();
         let mut stage_reads: Vec<ResourceVec> = vec![];
         let mut stage_writes: Vec<ResourceVec> = vec![];
+        let mut stage_concurrent_writes: Vec<ResourceVec> = vec![];
         let mut systems: Vec<_> = iter::repeat_with(|| None).take(num_systems).collect();
         let mut stage_systems = vec![];
 
+        #[cfg(feature = "serde")]
+        let mut system_names = vec![None; num_systems];
+
         let mut counter = 0;
         for stage in stages {
             let mut stage_read = vec![];
             let mut stage_write = vec![];
+            let mut stage_concurrent_write = vec![];
             let mut systems_in_stage = smallvec![];
 
             for system in stage {
                 let id = system.id();
                 system_reads[id.0] = read_deps[counter].iter().copied().collect();
                 system_writes[id.0] = write_deps[counter].iter().copied().collect();
+                system_concurrent_writes[id.0] =
+                    concurrent_write_deps[counter].iter().copied().collect();
                 stage_read.extend(system_reads[id.0].clone());
                 stage_write.extend(system_writes[id.0].clone());
+                stage_concurrent_write.extend(system_concurrent_writes[id.0].clone());
+                #[cfg(feature = "serde")]
+                {
+                    system_names[id.0] = Some(system.name());
+                }
                 systems[id.0] = Some(system);
                 systems_in_stage.push(id);
                 counter += 1;
@@ -287,33 +582,61 @@ impl Scheduler {
 
             stage_reads.push(stage_read.into_iter().collect());
             stage_writes.push(stage_write.into_iter().collect());
+            stage_concurrent_writes.push(stage_concurrent_write.into_iter().collect());
             stage_systems.push(systems_in_stage);
         }
 
         // Construct event handlers
-        let construct_end_of_dispatch_handlers = end_of_dispatch_handlers
-            .iter()
-            .map(|handlers| handlers.iter().map(|handler| handler.id()).collect())
-            .collect();
-
-        let mut event_handlers = Vec::with_capacity(end_of_dispatch_handlers.len());
-        let mut event_reads: Vec<ResourceVec> = vec![];
-        let mut event_writes: Vec<ResourceVec> = vec![];
+        let mut event_handlers = vec![];
+        let mut event_stages = Vec::with_capacity(end_of_dispatch_handlers.len());
+        let mut event_reads = Vec::with_capacity(end_of_dispatch_handlers.len());
+        let mut event_writes = Vec::with_capacity(end_of_dispatch_handlers.len());
+        let mut event_concurrent_writes = Vec::with_capacity(end_of_dispatch_handlers.len());
+
+        for pipeline in end_of_dispatch_handlers {
+            let mut pipeline_stages = vec![];
+            let mut pipeline_reads = vec![];
+            let mut pipeline_writes = vec![];
+            let mut pipeline_concurrent_writes = vec![];
+
+            for sub_stage in pipeline {
+                let mut handlers_in_stage: SmallVec<[SystemId; 4]> = smallvec![];
+                let mut stage_read = ResourceVec::new();
+                let mut stage_write = ResourceVec::new();
+                let mut stage_concurrent_write = ResourceVec::new();
+
+                for handler in sub_stage {
+                    stage_read.extend(handler.resource_reads().iter().copied());
+                    stage_write.extend(handler.resource_writes().iter().copied());
+                    stage_concurrent_write
+                        .extend(handler.resource_concurrent_writes().iter().copied());
+
+                    handlers_in_stage.push(handler.id());
+                    #[cfg(feature = "serde")]
+                    system_names.set_or_extend(handler.id().0, Some(handler.name()));
+                    *event_handlers.get_mut_or_extend(handler.id().0) = Some(handler);
+                }
 
-        for handler in end_of_dispatch_handlers.into_iter().flatten() {
-            let id = handler.id().0;
-            let option = event_handlers.get_mut_or_extend(id);
+                pipeline_stages.push(handlers_in_stage);
+                pipeline_reads.push(stage_read);
+                pipeline_writes.push(stage_write);
+                pipeline_concurrent_writes.push(stage_concurrent_write);
+            }
 
-            let event_id = handler.event_id().0;
+            event_stages.push(pipeline_stages);
+            event_reads.push(pipeline_reads);
+            event_writes.push(pipeline_writes);
+            event_concurrent_writes.push(pipeline_concurrent_writes);
+        }
 
-            event_reads
-                .get_mut_or_extend(event_id)
-                .extend(handler.resource_reads().iter().copied());
-            event_writes
-                .get_mut_or_extend(event_id)
-                .extend(handler.resource_writes().iter().copied());
+        if unhandled_event_policy == UnhandledEventPolicy::Collect {
+            resources.insert_if_absent(UnhandledEvents::default());
+        }
 
-            *option = Some(handler);
+        // Insert event channels so that events can be pushed
+        // into them before any reader has been initialized.
+        for hooks in event_channels.iter().flatten() {
+            (hooks.insert)(&mut resources);
         }
 
         // We use a bounded channel because the only overhead
@@ -321,7 +644,7 @@ impl Scheduler {
         // plow through messages. This may be changed in the future.
         let (sender, receiver) = crossbeam::bounded(8);
 
-        let bump = ThreadLocal::new();
+        let bump = BumpArenas::new();
 
         let starting_queue = Self::create_task_queue(&stage_systems);
 
@@ -333,6 +656,7 @@ impl Scheduler {
 
             writes_held: BitSet::new(),
             reads_held: vec![0; RESOURCE_ID_MAPPINGS.lock().len()],
+            concurrent_writes_held: vec![0; RESOURCE_ID_MAPPINGS.lock().len()],
 
             runnning_systems_count: 0,
             running_systems: BitSet::with_capacity(systems.len()),
@@ -342,14 +666,44 @@ impl Scheduler {
 
             system_reads,
             system_writes,
+            system_concurrent_writes,
             stage_reads,
             stage_writes,
+            stage_concurrent_writes,
 
             event_handlers,
-            end_of_tick_handlers: construct_end_of_dispatch_handlers,
+            event_stages,
 
             event_reads,
             event_writes,
+            event_concurrent_writes,
+
+            event_channels,
+            pending_events: vec![],
+            owned_events: vec![],
+
+            cascades: vec![],
+            handled_cascades: iter::repeat(None).take(num_systems).collect(),
+            max_cascade_depth,
+            cascade_error: None,
+
+            coalesce_events,
+            queued_events: vec![],
+
+            unhandled_event_policy,
+            unhandled_events: vec![],
+
+            tick: Arc::new(AtomicU64::new(0)),
+            scheduled_events: BinaryHeap::new(),
+            timed_events: BinaryHeap::new(),
+            scheduled_count: 0,
+
+            #[cfg(feature = "serde")]
+            event_registry,
+            #[cfg(feature = "serde")]
+            journal: EventJournal::new(),
+            #[cfg(feature = "serde")]
+            system_names,
 
             bump: Arc::new(bump),
 
@@ -374,43 +728,125 @@ impl Scheduler {
     }
 
     /// Executes all systems and handles events.
+    ///
+    /// # Panics
+    /// Panics if a cascade of events exceeds the maximum depth. The panic
+    /// occurs once all running systems and event handlers have completed;
+    /// use `try_execute` to handle the error instead.
     pub fn execute(&mut self, world: &mut World) {
+        if let Err(e) = self.try_execute(world) {
+            panic!("{}", e);
+        }
+    }
+
+    /// Executes all systems and handles events, returning an error
+    /// if a cascade of events exceeds the maximum depth.
+    ///
+    /// When this happens, no further batches of events are handled
+    /// during the dispatch, but all systems still run. The error is
+    /// returned once all running systems and event handlers have completed,
+    /// and the scheduler may be executed again afterwards.
+    pub fn try_execute(&mut self, world: &mut World) -> Result<(), CascadeError> {
         if self.is_first_run {
             self.is_first_run = false;
 
             self.on_first_run(world);
         }
 
+        self.trigger_scheduled_events();
+
         // Reset the task queue to the starting queue.
         self.task_queue.extend(self.starting_queue.iter().copied());
 
-        // While there are remaining tasks, dispatch them.
-        // When we encounter a task which can't be run because
-        // of conflicting dependencies, we wait for tasks to
-        // complete by listening on the channel.
-        while let Some(task) = self.task_queue.pop_front() {
-            // Attempt to run task.
-            self.run_task(task, world);
-        }
-
-        // Wait for remaining systems to complete.
-        while self.runnning_systems_count > 0 {
-            let num = self.wait_for_completion();
-            self.runnning_systems_count -= num;
-
-            // Run any handlers/oneshots scheduled by these systems
+        loop {
+            // While there are remaining tasks, dispatch them.
+            // When we encounter a task which can't be run because
+            // of conflicting dependencies, we wait for tasks to
+            // complete by listening on the channel.
             while let Some(task) = self.task_queue.pop_front() {
+                // Attempt to run task.
                 self.run_task(task, world);
             }
+
+            if self.runnning_systems_count > 0 {
+                // Wait for remaining systems to complete, then run any
+                // handlers/oneshots scheduled by these systems.
+                let num = self.wait_for_completion();
+                self.runnning_systems_count -= num;
+            } else if !self.schedule_queued_events() {
+                // Nothing is running and no events remain to be handled.
+                break;
+            }
         }
 
         assert!(self.task_queue.is_empty());
         assert!(self.running_systems.is_empty());
+
+        // No readers are running, so all pending events can be flushed.
+        self.flush_pending_events();
+        debug_assert!(self.pending_events.is_empty());
+
+        for hooks in self.event_channels.iter().flatten() {
+            (hooks.swap)(&mut self.resources, hooks.channel_id);
+        }
+
+        self.cascades.clear();
+
+        // Systems may access `UnhandledEvents`, so it is
+        // only updated once no systems are running.
+        let unhandled_events = mem::replace(&mut self.unhandled_events, vec![]);
+        if self.unhandled_event_policy == UnhandledEventPolicy::Collect {
+            let unhandled = self.resources.get_mut::<UnhandledEvents>();
+            for (id, count) in &unhandled_events {
+                unhandled.record(*id, *count);
+            }
+        }
+
+        // All handlers have completed, so events are no longer accessed.
+        self.drop_owned_events();
+
+        self.tick.fetch_add(1, Ordering::AcqRel);
+
+        // Safety: systems and event handlers only access the bump allocators
+        // while running, and none are running. All events have been dropped
+        // or moved into event channels, and all other allocations are only
+        // used during the dispatch.
+        unsafe {
+            self.bump.reset();
+        }
+
+        // Likewise, panicking is deferred until the dispatch has
+        // completed so that no task outlives the scheduler.
+        if self.unhandled_event_policy == UnhandledEventPolicy::Panic {
+            if let Some((id, _)) = unhandled_events.first() {
+                panic!(
+                    "event of type {} was triggered, but no event handlers or readers exist",
+                    event_name(*id)
+                );
+            }
+        }
+
+        match self.cascade_error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Drops all events owned by the scheduler.
+    fn drop_owned_events(&mut self) {
+        for events in self.owned_events.drain(..) {
+            // Safety: owned events are not moved into a channel,
+            // and coalesced copies of them are never dropped.
+            unsafe {
+                drop_events(events.id, events.ptr, events.len);
+            }
+        }
     }
 
     fn on_first_run(&mut self, world: &mut World) {
         let sender = self.sender.clone();
         let bump = Arc::clone(&self.bump);
+        let tick = Arc::clone(&self.tick);
         let resources = &mut self.resources;
 
         // Initialize all systems and event handlers.
@@ -424,6 +860,7 @@ impl Scheduler {
                     sender: sender.clone(),
                     id: sys.id(),
                     bump: Arc::clone(&bump),
+                    tick: Arc::clone(&tick),
                 };
 
                 sys.init(resources, ctx, world);
@@ -439,6 +876,7 @@ impl Scheduler {
                     sender: sender.clone(),
                     id: handler.id(),
                     bump: Arc::clone(&bump),
+                    tick: Arc::clone(&tick),
                 };
 
                 handler.init(resources, ctx, world);
@@ -452,14 +890,399 @@ impl Scheduler {
         E: Event,
     {
         let id = event_id_for::<E>();
-        // Don't trigger events which have no handlers.
-        if self.end_of_tick_handlers.len() <= id.0 {
+        #[cfg(feature = "serde")]
+        self.record_events(id, None, &event as *const E as *const (), 1);
+
+        let has_channel = self.event_channels.get(id.0).map_or(false, Option::is_some);
+        // Don't trigger events which have no handlers or readers.
+        if !self.has_handlers(id) && !has_channel {
+            self.report_unhandled_events(id, 1);
             return;
         }
 
-        let ptr = self.bump.get_or_default().alloc(event) as *mut E as *const ();
+        let ptr = self.bump.get().alloc(event) as *mut E as *const ();
         let len = 1;
-        self.task_queue.push_front(Task::HandleEvent(id, ptr, len));
+
+        if has_channel {
+            self.pending_events.push(PendingEvents { id, ptr, len });
+            self.flush_pending_events();
+        } else {
+            self.owned_events.push(PendingEvents { id, ptr, len });
+        }
+
+        if self.has_handlers(id) {
+            if self.coalesce_events {
+                self.queued_events.push(QueuedEvents {
+                    id,
+                    parent: None,
+                    ptr,
+                    len,
+                });
+            } else if let Some(batch) = self.create_event_batch(id, None, ptr, len) {
+                self.task_queue.push_front(Task::HandleEvent(batch, 0));
+            }
+        }
+    }
+
+    /// Triggers an event of a dynamic event type of bytes manually.
+    /// It will be handled on the next call to `execute()`. See `dynamic_event_id`.
+    ///
+    /// # Panics
+    /// Panics if the event type is not a dynamic event type of bytes,
+    /// or if the length of `bytes` does not match its layout.
+    pub fn trigger_bytes(&mut self, id: EventId, bytes: &[u8]) {
+        let ptr = alloc_bytes(self.bump.get(), id, bytes);
+        self.handle_triggered_events(id, None, None, ptr, 1);
+    }
+
+    /// Triggers an event of a boxed dynamic event type manually.
+    /// It will be handled on the next call to `execute()`. See `boxed_event_id`.
+    ///
+    /// # Panics
+    /// Panics if the event type is not a boxed dynamic event type.
+    pub fn trigger_boxed(&mut self, id: EventId, payload: BoxedEvent) {
+        let ptr = alloc_boxed(self.bump.get(), id, payload);
+        self.handle_triggered_events(id, None, None, ptr, 1);
+    }
+
+    /// Returns the tick of the current dispatch, or of the next
+    /// dispatch if none is running. This is the number of
+    /// calls to `execute()` which have completed.
+    pub fn tick(&self) -> u64 {
+        self.tick.load(Ordering::Acquire)
+    }
+
+    /// Triggers an event which will be handled during the
+    /// dispatch `ticks` ticks after the next one.
+    ///
+    /// A delay of 0 is equivalent to `trigger`.
+    pub fn trigger_after<E>(&mut self, event: E, ticks: u64)
+    where
+        E: Event,
+    {
+        let tick = self.tick() + ticks;
+        self.trigger_at(event, tick);
+    }
+
+    /// Triggers an event which will be handled during the dispatch
+    /// with the given tick. See `tick()`.
+    ///
+    /// If the tick is not after the next one, this is equivalent to `trigger`.
+    pub fn trigger_at<E>(&mut self, event: E, tick: u64)
+    where
+        E: Event,
+    {
+        if tick <= self.tick() {
+            self.trigger(event);
+        } else {
+            self.schedule_events(
+                event_id_for::<E>(),
+                None,
+                ScheduledTime::Tick(tick),
+                Box::new(vec![event]),
+            );
+        }
+    }
+
+    /// Triggers an event which will be handled during the first
+    /// dispatch which starts once the given delay has elapsed.
+    pub fn trigger_in<E>(&mut self, event: E, delay: Duration)
+    where
+        E: Event,
+    {
+        self.schedule_events(
+            event_id_for::<E>(),
+            None,
+            ScheduledTime::Instant(Instant::now() + delay),
+            Box::new(vec![event]),
+        );
+    }
+
+    /// Stores a batch of events to be triggered at the given time.
+    fn schedule_events(
+        &mut self,
+        id: EventId,
+        source: Option<SystemId>,
+        time: ScheduledTime,
+        events: Box<dyn ScheduledBatch>,
+    ) {
+        let seq = self.scheduled_count;
+        self.scheduled_count += 1;
+
+        match time {
+            ScheduledTime::Tick(at) => self.scheduled_events.push(ScheduledEvents {
+                at,
+                seq,
+                id,
+                source,
+                events,
+            }),
+            ScheduledTime::Instant(at) => self.timed_events.push(ScheduledEvents {
+                at,
+                seq,
+                id,
+                source,
+                events,
+            }),
+        }
+    }
+
+    /// Triggers all scheduled events which are due during the current dispatch.
+    fn trigger_scheduled_events(&mut self) {
+        let tick = self.tick();
+        while self
+            .scheduled_events
+            .peek()
+            .map_or(false, |events| events.at <= tick)
+        {
+            let events = self.scheduled_events.pop().unwrap();
+            self.trigger_scheduled(events.id, events.source, None, events.events);
+        }
+
+        let now = Instant::now();
+        while self
+            .timed_events
+            .peek()
+            .map_or(false, |events| events.at <= now)
+        {
+            let events = self.timed_events.pop().unwrap();
+            self.trigger_scheduled(events.id, events.source, None, events.events);
+        }
+    }
+
+    /// Moves a batch of scheduled events into the bump allocator and triggers them.
+    fn trigger_scheduled(
+        &mut self,
+        id: EventId,
+        source: Option<SystemId>,
+        parent: Option<usize>,
+        events: Box<dyn ScheduledBatch>,
+    ) {
+        let (ptr, len) = events.move_into(self.bump.get());
+        self.handle_triggered_events(id, source, parent, ptr, len);
+    }
+
+    /// Returns the journal of recorded events. This is empty
+    /// unless `EventsBuilder::set_event_journal` was called.
+    #[cfg(feature = "serde")]
+    pub fn journal(&self) -> &EventJournal {
+        &self.journal
+    }
+
+    /// Takes the journal of recorded events, leaving an empty journal
+    /// in its place. Further events continue to be recorded.
+    #[cfg(feature = "serde")]
+    pub fn take_journal(&mut self) -> EventJournal {
+        mem::replace(&mut self.journal, EventJournal::new())
+    }
+
+    /// Re-triggers the events in a journal, each during the dispatch with
+    /// the tick it was recorded at. To reproduce a recorded run, this should
+    /// be called on a freshly built scheduler before its first dispatch.
+    ///
+    /// Events triggered by systems will be triggered again if those systems
+    /// exist in this scheduler; use `EventJournal::retain` to skip them.
+    #[cfg(feature = "serde")]
+    pub fn replay(&mut self, journal: EventJournal) {
+        for entry in journal.into_entries() {
+            let (id, tick, source, events) = entry.into_scheduled();
+            // Sources are recorded by name, so they are
+            // kept for systems which exist in this scheduler.
+            let source = source.and_then(|name| {
+                self.system_names
+                    .iter()
+                    .position(|system| *system == Some(name.as_str()))
+                    .map(SystemId)
+            });
+            self.schedule_events(id, source, ScheduledTime::Tick(tick), events);
+        }
+    }
+
+    /// Records a batch of events in the journal if recording is enabled.
+    #[cfg(feature = "serde")]
+    fn record_events(&mut self, id: EventId, source: Option<SystemId>, ptr: *const (), len: usize) {
+        let tick = self.tick();
+        if let Some(registry) = &self.event_registry {
+            let source =
+                source.and_then(|source| self.system_names.get(source.0).copied().flatten());
+            // Safety: the pointer is guaranteed to point to events of the given type.
+            if let Some(entry) = unsafe { registry.record(id, tick, source, ptr, len) } {
+                self.journal.push(entry);
+            }
+        }
+    }
+
+    /// Coalesces all queued batches of each event type into one batch
+    /// and schedules handling of these batches.
+    ///
+    /// Returns whether any events were scheduled.
+    fn schedule_queued_events(&mut self) -> bool {
+        if self.queued_events.is_empty() || self.cascade_error.is_some() {
+            return false;
+        }
+
+        let queued = mem::replace(&mut self.queued_events, vec![]);
+
+        let mut ids: Vec<EventId> = vec![];
+        for events in &queued {
+            if !ids.contains(&events.id) {
+                ids.push(events.id);
+            }
+        }
+
+        for id in ids {
+            let batches: SmallVec<[QueuedEvents; 8]> = queued
+                .iter()
+                .filter(|events| events.id == id)
+                .copied()
+                .collect();
+
+            let (ptr, len) = self.coalesce_batches(id, &batches);
+
+            // Continue the deepest cascade among the batches.
+            let cascades = &self.cascades;
+            let parent = batches
+                .iter()
+                .filter_map(|events| events.parent)
+                .max_by_key(|parent| cascades[*parent].depth);
+
+            if let Some(batch) = self.create_event_batch(id, parent, ptr, len) {
+                self.task_queue.push_back(Task::HandleEvent(batch, 0));
+            }
+        }
+
+        true
+    }
+
+    /// Copies batches of events into one contiguous slice allocated
+    /// in the bump allocator, returning a pointer to the slice and its length.
+    ///
+    /// The events are moved bitwise, so they must only
+    /// be accessed through the returned slice.
+    fn coalesce_batches(&self, id: EventId, batches: &[QueuedEvents]) -> (*const (), usize) {
+        let len = batches.iter().map(|events| events.len).sum();
+
+        let layout = event_layout(id);
+        if batches.len() == 1 || layout.size() == 0 {
+            return (batches[0].ptr, len);
+        }
+
+        // The size of a type is always a multiple of its alignment,
+        // so the events are laid out as in a slice.
+        let slice_layout = Layout::from_size_align(layout.size() * len, layout.align()).unwrap();
+        let ptr = self.bump.get().alloc_layout(slice_layout).as_ptr();
+
+        let mut offset = 0;
+        for events in batches {
+            let size = layout.size() * events.len;
+            // Safety: each batch is a valid slice of events of this type.
+            unsafe {
+                ptr::copy_nonoverlapping(events.ptr as *const u8, ptr.add(offset), size);
+            }
+            offset += size;
+        }
+
+        (ptr as *const (), len)
+    }
+
+    /// Handles events which have no handlers or readers
+    /// according to the `UnhandledEventPolicy`.
+    fn report_unhandled_events(&mut self, id: EventId, count: usize) {
+        match self.unhandled_event_policy {
+            UnhandledEventPolicy::Ignore => (),
+            UnhandledEventPolicy::Log => {
+                #[cfg(feature = "log")]
+                log::warn!(
+                    "{} event(s) of type {} were triggered, but no event handlers or readers exist",
+                    count,
+                    event_name(id)
+                );
+            }
+            UnhandledEventPolicy::Collect => self.unhandled_events.push((id, count)),
+            UnhandledEventPolicy::Panic => {
+                // The panic is raised at the end of `execute()`.
+                if cfg!(debug_assertions) {
+                    self.unhandled_events.push((id, count));
+                }
+            }
+        }
+    }
+
+    /// Returns whether any end-of-tick handlers exist for the given event.
+    fn has_handlers(&self, id: EventId) -> bool {
+        self.event_stages
+            .get(id.0)
+            .map_or(false, |stages| !stages.is_empty())
+    }
+
+    /// Creates a batch of events to be handled, allocating its propagation flags.
+    ///
+    /// `parent` is the index into `cascades` of the batch whose
+    /// handlers triggered these events, if any.
+    ///
+    /// Returns `None` if the events should not be handled because a cascade
+    /// exceeded the maximum depth. In that case, batches which have not yet
+    /// been dispatched are discarded as well; running tasks are left to complete.
+    /// The events themselves are still dropped at the end of the dispatch.
+    fn create_event_batch(
+        &mut self,
+        id: EventId,
+        parent: Option<usize>,
+        ptr: *const (),
+        len: usize,
+    ) -> Option<EventBatch> {
+        if self.cascade_error.is_some() {
+            return None;
+        }
+
+        let depth = parent.map_or(0, |parent| self.cascades[parent].depth + 1);
+        if depth > self.max_cascade_depth {
+            self.cascade_error = Some(CascadeError {
+                max_depth: self.max_cascade_depth,
+                chain: self.describe_cascade(id, parent),
+            });
+
+            self.queued_events.clear();
+            self.task_queue.retain(|task| match task {
+                Task::HandleEvent(_, stage) => *stage != 0,
+                _ => true,
+            });
+            return None;
+        }
+
+        let cascade = self.cascades.len();
+        self.cascades.push(CascadeLink {
+            event: id,
+            parent,
+            depth,
+        });
+
+        let stopped = self
+            .bump
+            .get()
+            .alloc_slice_fill_with(len, |_| AtomicBool::new(false))
+            .as_ptr();
+
+        Some(EventBatch {
+            id,
+            ptr,
+            len,
+            stopped,
+            cascade,
+        })
+    }
+
+    /// Returns the names of the chain of events which
+    /// led to the triggering of the event with the given ID.
+    fn describe_cascade(&self, id: EventId, mut parent: Option<usize>) -> Vec<&'static str> {
+        let mut chain = vec![event_name(id)];
+        while let Some(link) = parent {
+            chain.push(event_name(self.cascades[link].event));
+            parent = self.cascades[link].parent;
+        }
+
+        chain.reverse();
+        chain
     }
 
     fn run_task(&mut self, task: Task, world: &mut World) {
@@ -475,10 +1298,16 @@ impl Scheduler {
             &self.event_writes,
             &task,
         );
+        let concurrent_writes = concurrent_writes_for_task(
+            &self.stage_concurrent_writes,
+            &self.system_concurrent_writes,
+            &self.event_concurrent_writes,
+            &task,
+        );
 
         // For event handlers, we have to check that the handler is not already running, since it takes &mut self.
-        let not_running = if let Task::HandleEvent(id, _, _) = &task {
-            if self.end_of_tick_handlers[id.0]
+        let not_running = if let Task::HandleEvent(batch, stage) = &task {
+            if self.event_stages[batch.id.0][*stage]
                 .iter()
                 .any(|id| self.running_systems.contains(id.0))
             {
@@ -490,18 +1319,26 @@ impl Scheduler {
             Ok(())
         };
 
-        match try_obtain_resources(reads, writes, &mut self.reads_held, &mut self.writes_held)
-            .and(not_running)
+        match try_obtain_resources(
+            reads,
+            writes,
+            concurrent_writes,
+            &mut self.reads_held,
+            &mut self.writes_held,
+            &mut self.concurrent_writes_held,
+        )
+        .and(not_running)
         {
             Ok(()) => {
                 // Run task and proceed.
                 #[cfg(feature = "log")]
                 {
                     log::trace!(
-                        "Dispatching task of type {:?} (reads: {:?}, writes: {:?})",
+                        "Dispatching task of type {:?} (reads: {:?}, writes: {:?}, concurrent writes: {:?})",
                         task,
                         reads,
-                        writes
+                        writes,
+                        concurrent_writes
                     );
                 }
                 let systems = self.dispatch_task(task, world);
@@ -532,39 +1369,145 @@ impl Scheduler {
             // TODO: events
             TaskMessage::SystemComplete(id) => {
                 self.release_resources_for_system(id);
+                self.flush_pending_events();
                 self.running_systems.remove(id.0);
                 1
             }
             TaskMessage::StageComplete(id) => {
                 self.release_resources_for_stage(id);
+                self.flush_pending_events();
                 let running_systems = &mut self.running_systems;
                 self.stages[id.0].iter().for_each(|id| {
                     running_systems.remove(id.0);
                 });
                 self.stages[id.0].len()
             }
-            TaskMessage::TriggerEvents { id, ptr, len } => {
-                if self.end_of_tick_handlers.len() <= id.0 {
-                    return 0;
+            TaskMessage::TriggerEvents {
+                id,
+                source,
+                ptr,
+                len,
+            } => {
+                // Events triggered by a handler continue the cascade
+                // of the events it is handling.
+                let parent = self.handled_cascades.get(source.0).copied().flatten();
+                self.handle_triggered_events(id, Some(source), parent, ptr, len);
+                0
+            }
+            TaskMessage::ScheduleEvents {
+                id,
+                source,
+                time,
+                events,
+            } => {
+                match time {
+                    // Events due during the current dispatch are triggered immediately.
+                    ScheduledTime::Tick(tick) if tick <= self.tick() => {
+                        let parent = self.handled_cascades.get(source.0).copied().flatten();
+                        self.trigger_scheduled(id, Some(source), parent, events);
+                    }
+                    _ => self.schedule_events(id, Some(source), time, events),
                 }
-
-                self.task_queue.push_back(Task::HandleEvent(id, ptr, len));
                 0
             }
-            TaskMessage::EventHandlingComplete(id) => {
-                self.release_resources_for_event_handler(id);
+            TaskMessage::EventHandlingComplete(batch, stage) => {
+                self.release_resources_for_event_handlers(batch.id, stage);
+                self.flush_pending_events();
                 let running_systems = &mut self.running_systems;
-                self.end_of_tick_handlers[id.0].iter().for_each(|id| {
+                let handled_cascades = &mut self.handled_cascades;
+                let stages = &self.event_stages[batch.id.0];
+                stages[stage].iter().for_each(|id| {
                     running_systems.remove(id.0);
+                    handled_cascades[id.0] = None;
                 });
-                self.end_of_tick_handlers[id.0].len()
+
+                // Run the next sub-stage on the same events.
+                if stage + 1 < stages.len() {
+                    self.task_queue
+                        .push_front(Task::HandleEvent(batch, stage + 1));
+                }
+
+                self.event_stages[batch.id.0][stage].len()
             }
         }
     }
 
+    /// Moves triggered events into their event channel
+    /// and schedules them for handling.
+    ///
+    /// `source` is the system or event handler which triggered the events,
+    /// and `parent` is the index into `cascades` of the batch whose
+    /// handlers triggered these events, if any.
+    fn handle_triggered_events(
+        &mut self,
+        id: EventId,
+        source: Option<SystemId>,
+        parent: Option<usize>,
+        ptr: *const (),
+        len: usize,
+    ) {
+        #[cfg(feature = "serde")]
+        self.record_events(id, source, ptr, len);
+        #[cfg(not(feature = "serde"))]
+        let _ = source;
+
+        // Events are either moved into their channel or dropped
+        // by the scheduler, even if they have no handlers.
+        if let Some(Some(_)) = self.event_channels.get(id.0) {
+            self.pending_events.push(PendingEvents { id, ptr, len });
+            self.flush_pending_events();
+        } else {
+            self.owned_events.push(PendingEvents { id, ptr, len });
+        }
+
+        if !self.has_handlers(id) {
+            if self.event_channels.get(id.0).map_or(true, Option::is_none) {
+                self.report_unhandled_events(id, len);
+            }
+            return;
+        }
+
+        if self.coalesce_events {
+            self.queued_events.push(QueuedEvents {
+                id,
+                parent,
+                ptr,
+                len,
+            });
+        } else if let Some(batch) = self.create_event_batch(id, parent, ptr, len) {
+            self.task_queue.push_back(Task::HandleEvent(batch, 0));
+        }
+    }
+
+    /// Moves pending events into their event channels, skipping
+    /// channels which are currently being read.
+    ///
+    /// Events remain readable through their original pointers,
+    /// so they can still be passed to event handlers.
+    fn flush_pending_events(&mut self) {
+        let resources = &self.resources;
+        let reads_held = &self.reads_held;
+        let event_channels = &self.event_channels;
+
+        self.pending_events.retain(|events| {
+            let hooks = event_channels[events.id.0].as_ref().unwrap();
+            if reads_held[hooks.channel_id.0] > 0 {
+                return true;
+            }
+
+            // Safety: no task is reading the channel, and the scheduler
+            // is the only one which writes to it.
+            unsafe {
+                (hooks.push)(resources, hooks.channel_id, events.ptr, events.len);
+            }
+            false
+        });
+    }
+
     fn release_resources_for_system(&mut self, id: SystemId) {
         let reads = &self.system_reads[id.0];
         let writes = &self.system_writes[id.0];
+        let concurrent_writes = &self.system_concurrent_writes[id.0];
 
         for read in reads {
             self.reads_held[read.0] -= 1;
@@ -573,6 +1516,10 @@ impl Scheduler {
         for write in writes {
             self.writes_held.remove(write.0);
         }
+
+        for write in concurrent_writes {
+            self.concurrent_writes_held[write.0] -= 1;
+        }
     }
 
     fn release_resources_for_stage(&mut self, id: StageId) {
@@ -583,11 +1530,16 @@ impl Scheduler {
         for write in &self.stage_writes[id.0] {
             self.writes_held.remove(write.0);
         }
+
+        for write in &self.stage_concurrent_writes[id.0] {
+            self.concurrent_writes_held[write.0] -= 1;
+        }
     }
 
-    fn release_resources_for_event_handler(&mut self, id: EventId) {
-        let reads = &self.event_reads[id.0];
-        let writes = &self.event_writes[id.0];
+    fn release_resources_for_event_handlers(&mut self, id: EventId, stage: usize) {
+        let reads = &self.event_reads[id.0][stage];
+        let writes = &self.event_writes[id.0][stage];
+        let concurrent_writes = &self.event_concurrent_writes[id.0][stage];
 
         for read in reads {
             self.reads_held[read.0] -= 1;
@@ -596,6 +1548,10 @@ impl Scheduler {
         for write in writes {
             self.writes_held.remove(write.0);
         }
+
+        for write in concurrent_writes {
+            self.concurrent_writes_held[write.0] -= 1;
+        }
     }
 
     /// Dispatches a task, returning the number of systems spawned.
@@ -614,17 +1570,19 @@ impl Scheduler {
                 self.dispatch_system(id, world);
                 1
             }
-            Task::HandleEvent(id, ptr, len) => {
+            Task::HandleEvent(batch, stage) => {
                 let running_systems = &mut self.running_systems;
-                let handlers = &self.end_of_tick_handlers[id.0];
+                let handled_cascades = &mut self.handled_cascades;
+                let handlers = &self.event_stages[batch.id.0][stage];
 
                 handlers.iter().for_each(|id| {
                     running_systems.insert(id.0);
+                    handled_cascades[id.0] = Some(batch.cascade);
                 });
 
-                self.dispatch_event_handlers(id, ptr, len, world);
+                self.dispatch_event_handlers(batch, stage, world);
 
-                let handlers = &self.end_of_tick_handlers[id.0];
+                let handlers = &self.event_stages[batch.id.0][stage];
                 handlers.len()
             }
         }
@@ -647,6 +1605,7 @@ impl Scheduler {
 
         let sender = self.sender.clone();
         let bump = Arc::clone(&self.bump);
+        let tick = Arc::clone(&self.tick);
 
         rayon::spawn(move || {
             unsafe {
@@ -658,6 +1617,7 @@ impl Scheduler {
                             id: *sys_id,
                             sender: sender.clone(),
                             bump: Arc::clone(&bump),
+                            tick: Arc::clone(&tick),
                         };
 
                         sys.execute_raw(&*resources.0, ctx, &*world.0);
@@ -694,43 +1654,50 @@ impl Scheduler {
         });
     }
 
-    fn dispatch_event_handlers(
-        &mut self,
-        id: EventId,
-        ptr: *const (),
-        len: usize,
-        world: &mut World,
-    ) {
+    fn dispatch_event_handlers(&mut self, batch: EventBatch, stage: usize, world: &mut World) {
         let handler_ids =
-            SharedRawPtr(&self.end_of_tick_handlers[id.0] as *const SmallVec<[SystemId; 4]>);
+            SharedRawPtr(&self.event_stages[batch.id.0][stage] as *const SmallVec<[SystemId; 4]>);
         let handlers =
             SharedMutRawPtr(&mut self.event_handlers as *mut Vec<Option<Box<dyn RawEventHandler>>>);
         let resources = SharedRawPtr(&self.resources as *const Resources);
         let sender = self.sender.clone();
-        let ptr = SharedRawPtr(ptr);
         let world = SharedRawPtr(world as *const World);
 
         let bump = Arc::clone(&self.bump);
+        let tick = Arc::clone(&self.tick);
 
         rayon::spawn(move || {
-            // Safety: see dispatch_system().
+            // Safety: see dispatch_system(). Handlers in the same
+            // sub-stage do not conflict with each other.
             unsafe {
+                let stopped = std::slice::from_raw_parts(batch.stopped, batch.len);
+
                 (&*handler_ids.0)
-                    .iter()
+                    .par_iter()
                     .map(|id| (id, (&mut *handlers.0)[id.0].as_mut().unwrap()))
                     .for_each(|(handler_id, handler)| {
-                        debug_assert_eq!(handler.event_id(), id);
+                        debug_assert_eq!(handler.event_id(), batch.id);
 
                         let ctx = SystemCtx {
                             id: *handler_id,
                             sender: sender.clone(),
                             bump: Arc::clone(&bump),
+                            tick: Arc::clone(&tick),
                         };
 
-                        handler.handle_raw_batch(ptr.0, len, &*resources.0, ctx, &*world.0);
+                        handler.handle_raw_batch_with_stopped(
+                            batch.ptr,
+                            batch.len,
+                            stopped,
+                            &*resources.0,
+                            ctx,
+                            &*world.0,
+                        );
                     });
 
-                sender.send(TaskMessage::EventHandlingComplete(id)).unwrap();
+                sender
+                    .send(TaskMessage::EventHandlingComplete(batch, stage))
+                    .unwrap();
             }
         });
     }
@@ -740,33 +1707,50 @@ impl Scheduler {
             sender: self.sender.clone(),
             id,
             bump: Arc::clone(&self.bump),
+            tick: Arc::clone(&self.tick),
         }
     }
 }
 
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        // Drop events triggered using `trigger()` which were never handled.
+        self.drop_owned_events();
+    }
+}
+
 /// Attempts to acquire resources for a task, returning `Err` if
 /// there was a conflict and `Ok` if successful.
 fn try_obtain_resources(
     reads: &ResourceVec,
     writes: &ResourceVec,
+    concurrent_writes: &ResourceVec,
     reads_held: &mut [u32],
     writes_held: &mut BitSet,
+    concurrent_writes_held: &mut [u32],
 ) -> Result<(), ()> {
     // First, go through resources and confirm that there are no conflicting
     // accessors.
-    // Since both read and write dependencies will only conflict with another resource
+    // Since all kinds of dependencies will conflict with another resource
     // access when there is another write access, we can interpret them in the same way.
-    for resource in reads.iter().chain(writes) {
+    for resource in reads.iter().chain(writes).chain(concurrent_writes) {
         if writes_held.contains(resource.0) {
             return Err(()); // Conflict
         }
     }
-    // Write resources will also conflict with existing read ones.
-    for resource in writes {
+    // Write and concurrent write resources will also conflict with existing read ones.
+    for resource in writes.iter().chain(concurrent_writes) {
         if reads_held[resource.0] > 0 {
             return Err(()); // Conflict
         }
     }
+    // Read and write resources will conflict with existing concurrent writes.
+    // Concurrent writes do not conflict with each other.
+    for resource in reads.iter().chain(writes) {
+        if concurrent_writes_held[resource.0] > 0 {
+            return Err(()); // Conflict
+        }
+    }
 
     // Now obtain resources by updating internal structures.
     for read in reads {
@@ -777,32 +1761,49 @@ fn try_obtain_resources(
         writes_held.insert(write.0);
     }
 
+    for write in concurrent_writes {
+        concurrent_writes_held[write.0] += 1;
+    }
+
     Ok(())
 }
 
 fn reads_for_task<'a>(
     stage_reads: &'a [ResourceVec],
     system_reads: &'a [ResourceVec],
-    event_reads: &'a [ResourceVec],
+    event_reads: &'a [Vec<ResourceVec>],
     task: &Task,
 ) -> &'a ResourceVec {
     match task {
         Task::Stage(id) => &stage_reads[id.0],
         Task::Oneshot(id) => &system_reads[id.0],
-        Task::HandleEvent(id, _, _) => &event_reads[id.0],
+        Task::HandleEvent(batch, stage) => &event_reads[batch.id.0][*stage],
     }
 }
 
 fn writes_for_task<'a>(
     stage_writes: &'a [ResourceVec],
     system_writes: &'a [ResourceVec],
-    event_writes: &'a [ResourceVec],
+    event_writes: &'a [Vec<ResourceVec>],
     task: &Task,
 ) -> &'a ResourceVec {
     match task {
         Task::Stage(id) => &stage_writes[id.0],
         Task::Oneshot(id) => &system_writes[id.0],
-        Task::HandleEvent(id, _, _) => &event_writes[id.0],
+        Task::HandleEvent(batch, stage) => &event_writes[batch.id.0][*stage],
+    }
+}
+
+fn concurrent_writes_for_task<'a>(
+    stage_concurrent_writes: &'a [ResourceVec],
+    system_concurrent_writes: &'a [ResourceVec],
+    event_concurrent_writes: &'a [Vec<ResourceVec>],
+    task: &Task,
+) -> &'a ResourceVec {
+    match task {
+        Task::Stage(id) => &stage_concurrent_writes[id.0],
+        Task::Oneshot(id) => &system_concurrent_writes[id.0],
+        Task::HandleEvent(batch, stage) => &event_concurrent_writes[batch.id.0][*stage],
     }
 }
 
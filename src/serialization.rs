@@ -0,0 +1,220 @@
+//! Serialization of resources using `serde`.
+//!
+//! Resources are serialized as a map from a stable, user-provided
+//! type name to the resource value. `ResourceId`s are not used because
+//! they depend on the order in which types are first encountered and
+//! thus differ between runs.
+
+use crate::resources::Resource;
+use crate::{resource_id_for, ResourceId, Resources};
+use hashbrown::HashMap;
+use serde::de::{DeserializeOwned, DeserializeSeed, Error as _, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// A serializable resource type registered in a `ResourceRegistry`.
+struct Registration {
+    /// Stable name of the resource type.
+    name: &'static str,
+    /// ID of the resource type.
+    id: ResourceId,
+    /// Returns the resource as an erased `Serialize`, or `None` if it
+    /// does not exist in the `Resources`.
+    serialize: for<'r> fn(&'r Resources) -> Option<&'r dyn erased_serde::Serialize>,
+    /// Deserializes the resource and inserts it into the `Resources`.
+    deserialize: for<'de> fn(
+        &mut dyn erased_serde::Deserializer<'de>,
+        &mut Resources,
+    ) -> Result<(), erased_serde::Error>,
+}
+
+/// Registry of resource types which can be serialized and deserialized.
+///
+/// Only resources whose types have been registered are serialized;
+/// all other resources are skipped.
+#[derive(Default)]
+pub struct ResourceRegistry {
+    registrations: Vec<Registration>,
+    /// Mappings from type names to indices into `registrations`.
+    by_name: HashMap<&'static str, usize>,
+}
+
+impl ResourceRegistry {
+    /// Creates an empty `ResourceRegistry`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a serializable resource type under the given name.
+    ///
+    /// The name is used as the key of the resource in serialized data,
+    /// so it should not change between versions of a program.
+    ///
+    /// # Panics
+    /// Panics if the type or the name has already been registered.
+    pub fn register<T>(&mut self, name: &'static str)
+    where
+        T: Resource + Serialize + DeserializeOwned,
+    {
+        let id = resource_id_for::<T>();
+        assert!(
+            !self.registrations.iter().any(|reg| reg.id == id),
+            "resource type {} registered twice",
+            std::any::type_name::<T>()
+        );
+        assert!(
+            !self.by_name.contains_key(name),
+            "resource name {} registered twice",
+            name
+        );
+
+        self.by_name.insert(name, self.registrations.len());
+        self.registrations.push(Registration {
+            name,
+            id,
+            serialize: serialize_resource::<T>,
+            deserialize: deserialize_resource::<T>,
+        });
+    }
+
+    /// Registers a serializable resource type, returning the
+    /// `ResourceRegistry` for method chaining.
+    pub fn with<T>(mut self, name: &'static str) -> Self
+    where
+        T: Resource + Serialize + DeserializeOwned,
+    {
+        self.register::<T>(name);
+        self
+    }
+
+    /// Returns a value which serializes all registered resources
+    /// contained in `resources`.
+    pub fn serialize<'a>(&'a self, resources: &'a Resources) -> ResourcesView<'a> {
+        ResourcesView {
+            registry: self,
+            resources,
+        }
+    }
+
+    /// Deserializes resources previously serialized with `serialize()`,
+    /// inserting them into `resources` and replacing any existing values.
+    ///
+    /// Returns an error if the data contains a resource name which
+    /// has not been registered.
+    pub fn deserialize_into<'de, D>(
+        &self,
+        deserializer: D,
+        resources: &mut Resources,
+    ) -> Result<(), D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(ResourcesVisitor {
+            registry: self,
+            resources,
+        })
+    }
+}
+
+/// Serializable view of the registered resources in a `Resources`.
+///
+/// This borrows the live resources; they are read when serialized.
+pub struct ResourcesView<'a> {
+    registry: &'a ResourceRegistry,
+    resources: &'a Resources,
+}
+
+impl<'a> Serialize for ResourcesView<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let entries: Vec<_> = self
+            .registry
+            .registrations
+            .iter()
+            .filter_map(|reg| (reg.serialize)(self.resources).map(|value| (reg.name, value)))
+            .collect();
+
+        let mut map = serializer.serialize_map(Some(entries.len()))?;
+        for (name, value) in entries {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+struct ResourcesVisitor<'a> {
+    registry: &'a ResourceRegistry,
+    resources: &'a mut Resources,
+}
+
+impl<'a, 'de> Visitor<'de> for ResourcesVisitor<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map of resource names to resources")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let resources = self.resources;
+
+        while let Some(name) = map.next_key::<String>()? {
+            let index = *self
+                .registry
+                .by_name
+                .get(name.as_str())
+                .ok_or_else(|| A::Error::custom(format!("unknown resource {}", name)))?;
+
+            map.next_value_seed(ResourceSeed {
+                registration: &self.registry.registrations[index],
+                resources: &mut *resources,
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Deserializes a single resource and inserts it into the `Resources`.
+struct ResourceSeed<'a> {
+    registration: &'a Registration,
+    resources: &'a mut Resources,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for ResourceSeed<'a> {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut erased = <dyn erased_serde::Deserializer<'de>>::erase(deserializer);
+        (self.registration.deserialize)(&mut erased, self.resources).map_err(D::Error::custom)
+    }
+}
+
+fn serialize_resource<T>(resources: &Resources) -> Option<&dyn erased_serde::Serialize>
+where
+    T: Resource + Serialize,
+{
+    resources
+        .try_get::<T>()
+        .map(|resource| resource as &dyn erased_serde::Serialize)
+}
+
+fn deserialize_resource<'de, T>(
+    deserializer: &mut dyn erased_serde::Deserializer<'de>,
+    resources: &mut Resources,
+) -> Result<(), erased_serde::Error>
+where
+    T: Resource + DeserializeOwned,
+{
+    let value: T = erased_serde::deserialize(deserializer)?;
+    resources.insert(value);
+    Ok(())
+}
@@ -0,0 +1,257 @@
+//! Split borrows of a `PreparedWorld`, allowing several
+//! queries to be iterated at once without `unsafe`.
+
+use crate::filter::{ArchetypeAccess, QueryFilter};
+use crate::query::FilteredView;
+use crate::system::SystemCtx;
+use crate::{MacroData, PreparedWorld, Query, ResourceId, Resources, SystemData, SystemDataOutput};
+use legion::entity::Entity;
+use legion::query::{Chunk, View};
+use legion::storage::ComponentTypeId;
+use legion::world::World;
+
+impl PreparedWorld {
+    /// Splits this world between the queries of a `Split`, so that
+    /// they can be iterated at the same time.
+    ///
+    /// ```ignore
+    /// #[system]
+    /// fn sys(
+    ///     queries: &mut Split<(Query<Write<Position>>, Query<Read<Velocity>>)>,
+    ///     world: &mut PreparedWorld,
+    /// ) {
+    ///     let (mut positions, mut velocities) = world.split(queries);
+    /// }
+    /// ```
+    pub fn split<'a, Q>(&'a mut self, queries: &'a mut Split<Q>) -> Q::Output
+    where
+        Q: SplitQueries<'a>,
+    {
+        queries.queries.split(self)
+    }
+}
+
+/// System data holding several queries which can be iterated
+/// at the same time using `PreparedWorld::split`, e.g. to look up
+/// entities of one query while mutably iterating another one.
+///
+/// The queries are held by the system rather than created by the split,
+/// since they cache matched archetypes and change-detection ticks between runs.
+///
+/// # Panics
+/// Adding a system with a `Split` to a `SchedulerBuilder` panics if one query
+/// writes a component accessed by another one, unless their filters ensure
+/// that they never match the same entities (e.g. `With<Player>` and `Without<Player>`).
+pub struct Split<Q> {
+    queries: Q,
+}
+
+impl<'a, Q> SystemData<'a> for Split<Q>
+where
+    Q: for<'b> SystemData<'b> + for<'b> SplitQueries<'b>,
+{
+    type Output = &'a mut Self;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        ctx: SystemCtx,
+        world: &World,
+    ) -> Self {
+        Self {
+            queries: Q::load_from_resources(resources, ctx, world),
+        }
+    }
+
+    fn init(
+        &mut self,
+        resources: &mut Resources,
+        component_reads: &[ComponentTypeId],
+        component_writes: &[ComponentTypeId],
+    ) {
+        self.queries
+            .init(resources, component_reads, component_writes);
+    }
+
+    fn validate() {
+        <Q as SystemData<'a>>::validate();
+
+        let accesses = <Q as SplitQueries<'a>>::accesses();
+        for (i, first) in accesses.iter().enumerate() {
+            for second in &accesses[i + 1..] {
+                assert!(
+                    !first
+                        .iter()
+                        .any(|access| second.iter().any(|other| access.conflicts_with(other))),
+                    "queries of {} access overlapping components",
+                    std::any::type_name::<Self>()
+                );
+            }
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        <Q as SystemData<'a>>::component_reads()
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        <Q as SystemData<'a>>::component_writes()
+    }
+
+    fn archetype_accesses() -> Vec<ArchetypeAccess> {
+        <Q as SystemData<'a>>::archetype_accesses()
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self.queries.before_execution();
+        self
+    }
+
+    fn after_execution(&mut self) {
+        self.queries.after_execution();
+    }
+}
+
+impl<'a, Q> SystemDataOutput<'a> for &'a mut Split<Q>
+where
+    Q: for<'b> SystemData<'b> + for<'b> SplitQueries<'b>,
+{
+    type SystemData = Split<Q>;
+}
+
+impl<Q> MacroData for &'static mut Split<Q>
+where
+    Q: for<'b> SystemData<'b> + for<'b> SplitQueries<'b> + Send + Sync + 'static,
+{
+    type SystemData = Split<Q>;
+}
+
+/// A tuple of queries which can be held by a `Split`.
+pub trait SplitQueries<'a> {
+    /// Tuple of `QueryBorrow`s, one for each query.
+    type Output;
+
+    /// Returns the component accesses of each query.
+    fn accesses() -> Vec<Vec<ArchetypeAccess>>;
+
+    /// Splits the world between the queries.
+    /// The queries must not conflict.
+    fn split(&'a mut self, world: &'a PreparedWorld) -> Self::Output;
+}
+
+macro_rules! impl_split_queries {
+    ($($view:ident, $filter:ident, $idx:tt);*) => {
+        impl<'a, $($view, $filter),*> SplitQueries<'a> for ($(Query<$view, $filter>,)*)
+        where
+            $($view: FilteredView<$filter>, $filter: QueryFilter,)*
+        {
+            type Output = ($(QueryBorrow<'a, $view, $filter>,)*);
+
+            fn accesses() -> Vec<Vec<ArchetypeAccess>> {
+                vec![$(<Query<$view, $filter> as SystemData<'a>>::archetype_accesses(),)*]
+            }
+
+            fn split(&'a mut self, world: &'a PreparedWorld) -> Self::Output {
+                ($(QueryBorrow { query: &mut self.$idx, world },)*)
+            }
+        }
+    };
+}
+
+impl_split_queries!(V0, F0, 0; V1, F1, 1);
+impl_split_queries!(V0, F0, 0; V1, F1, 1; V2, F2, 2);
+impl_split_queries!(V0, F0, 0; V1, F1, 1; V2, F2, 2; V3, F3, 3);
+
+/// A query together with its share of a split `PreparedWorld`.
+/// Created by `PreparedWorld::split`.
+pub struct QueryBorrow<'a, V, F>
+where
+    V: FilteredView<F>,
+    F: QueryFilter,
+{
+    query: &'a mut Query<V, F>,
+    world: &'a PreparedWorld,
+}
+
+impl<'a, V, F> QueryBorrow<'a, V, F>
+where
+    V: FilteredView<F>,
+    F: QueryFilter,
+{
+    // Safety: `Split::validate` ensures that no other query accesses the components
+    // of this query, and the `&mut PreparedWorld` borrowed by the split
+    // prevents any other access to the world.
+
+    /// Gets an iterator which iterates through all chunks that match the query.
+    pub fn iter_chunks<'b, 'c>(&'c mut self) -> impl Iterator<Item = Chunk<'b, V>> + 'c
+    where
+        'b: 'c,
+    {
+        unsafe { self.query.iter_chunks_unchecked(self.world) }
+    }
+
+    /// Gets an iterator which iterates through all entity data that matches the query, and also yields the the `Entity` IDs.
+    #[inline]
+    pub fn iter_entities<'b, 'data>(
+        &'b mut self,
+    ) -> impl Iterator<Item = (Entity, <<V as View<'data>>::Iter as Iterator>::Item)> + 'b
+    where
+        'data: 'b,
+    {
+        unsafe { self.query.iter_entities_unchecked(self.world) }
+    }
+
+    /// Gets an iterator which iterates through all entity data that matches the query.
+    #[inline]
+    pub fn iter<'b, 'data>(
+        &'b mut self,
+    ) -> impl Iterator<Item = <<V as View<'data>>::Iter as Iterator>::Item> + 'b
+    where
+        'data: 'b,
+    {
+        unsafe { self.query.iter_unchecked(self.world) }
+    }
+
+    /// Iterates through all entity data that matches the query.
+    #[inline]
+    pub fn for_each<'b, 'data, T>(&'b mut self, f: T)
+    where
+        T: Fn(<<V as View<'data>>::Iter as Iterator>::Item),
+    {
+        unsafe { self.query.for_each_unchecked(self.world, f) }
+    }
+
+    /// Iterates through all entity data that matches the query.
+    #[inline]
+    pub fn for_each_entities<'b, 'data, T>(&'b mut self, f: T)
+    where
+        T: Fn((Entity, <<V as View<'data>>::Iter as Iterator>::Item)),
+    {
+        unsafe { self.query.for_each_entities_unchecked(self.world, f) }
+    }
+
+    /// Iterates through all entities that matches the query in parallel by chunk.
+    #[inline]
+    pub fn par_entities_for_each<'b, T>(&'b mut self, f: T)
+    where
+        T: Fn((Entity, <<V as View<'b>>::Iter as Iterator>::Item)) + Send + Sync,
+    {
+        unsafe { self.query.par_entities_for_each_unchecked(self.world, f) }
+    }
+
+    /// Iterates through all entity data that matches the query in parallel.
+    #[inline]
+    pub fn par_for_each<'b, T>(&'b mut self, f: T)
+    where
+        T: Fn(<<V as View<'b>>::Iter as Iterator>::Item) + Send + Sync,
+    {
+        unsafe { self.query.par_for_each_unchecked(self.world, f) }
+    }
+}
@@ -1,16 +1,20 @@
-use crate::resources::Resource;
-use crate::scheduler::TaskMessage;
-use crate::{mappings::Mappings, resource_id_for, ResourceId, Resources, TryDefault};
-use bumpalo::Bump;
+use crate::filter::ArchetypeAccess;
+use crate::resources::{ChangeTracker, Resource};
+use crate::scheduler::{BumpArenas, TaskMessage};
+use crate::{
+    channel::EventChannelHooks, mappings::Mappings, resource_id_for, resource_id_for_keyed,
+    ResourceId, Resources, TryDefault,
+};
 use crossbeam::Sender;
 use lazy_static::lazy_static;
 use legion::storage::ComponentTypeId;
 use legion::world::World;
 use parking_lot::Mutex;
 use std::any::TypeId;
+use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
+use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
-use thread_local::ThreadLocal;
 
 /// Unique ID of a system, allocated consecutively for use as indices into vectors.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
@@ -48,10 +52,41 @@ pub trait RawSystem: Send + Sync {
     fn resource_reads(&self) -> &[ResourceId];
     /// Returns the resources written by this system.
     fn resource_writes(&self) -> &[ResourceId];
+    /// Returns the internally synchronized resources which this system
+    /// accesses concurrently. See `Concurrent`.
+    ///
+    /// The default implementation of this function returns an empty slice.
+    fn resource_concurrent_writes(&self) -> &[ResourceId] {
+        &[]
+    }
+    /// Returns the event channels read by this system. See `EventReader`.
+    ///
+    /// The default implementation of this function returns an empty slice.
+    fn event_channels(&self) -> &[EventChannelHooks] {
+        &[]
+    }
     /// Returns the components read by this system.
     fn component_reads(&self) -> &[ComponentTypeId];
     /// Returns the components written by this system.
     fn component_writes(&self) -> &[ComponentTypeId];
+    /// Returns the component accesses of this system along with
+    /// the archetypes they are restricted to.
+    ///
+    /// The default implementation of this function returns a single access
+    /// to `component_reads()` and `component_writes()` on all archetypes.
+    fn archetype_accesses(&self) -> Vec<ArchetypeAccess> {
+        vec![ArchetypeAccess::new(
+            self.component_reads().to_vec(),
+            self.component_writes().to_vec(),
+        )]
+    }
+    /// Called when archetype-level conflict detection is enabled.
+    /// Afterwards, random access through `PreparedWorld` must only be
+    /// granted for components whose accesses are unrestricted,
+    /// since other systems may access the same components on other archetypes.
+    ///
+    /// The default implementation of this function is a no-op.
+    fn restrict_random_access(&mut self) {}
 
     /// Initializes this system, inserting any necessary resources.
     fn init(&mut self, resources: &mut Resources, ctx: SystemCtx, world: &World);
@@ -80,10 +115,19 @@ pub struct CachedSystem<S: System> {
     pub(crate) resource_reads: Vec<ResourceId>,
     /// Cached resource writes.
     pub(crate) resource_writes: Vec<ResourceId>,
+    /// Cached concurrent resource writes.
+    pub(crate) resource_concurrent_writes: Vec<ResourceId>,
+    /// Cached event channels.
+    pub(crate) event_channels: Vec<EventChannelHooks>,
     /// Cached component reads.
     pub(crate) component_reads: Vec<ComponentTypeId>,
     /// Cached component writes.
     pub(crate) component_writes: Vec<ComponentTypeId>,
+    /// Cached archetype-level component accesses.
+    pub(crate) archetype_accesses: Vec<ArchetypeAccess>,
+    /// Whether random component access is restricted.
+    /// See `RawSystem::restrict_random_access`.
+    pub(crate) restrict_random_access: bool,
     /// Cached system data, or `None` if it has not yet been loaded.
     pub(crate) data: Option<S::SystemData>,
     pub(crate) name: &'static str,
@@ -91,12 +135,27 @@ pub struct CachedSystem<S: System> {
 
 impl<S: System + 'static> CachedSystem<S> {
     pub fn new(inner: S, name: &'static str) -> Self {
+        S::SystemData::validate();
+
+        let mut resource_reads = S::SystemData::resource_reads();
+        let mut resource_writes = S::SystemData::resource_writes();
+        let mut resource_concurrent_writes = S::SystemData::resource_concurrent_writes();
+        merge_concurrent_reads(
+            &mut resource_reads,
+            &mut resource_writes,
+            &mut resource_concurrent_writes,
+        );
+
         Self {
             id: SYSTEM_ID_MAPPINGS.lock().alloc(),
-            resource_reads: S::SystemData::resource_reads(),
-            resource_writes: S::SystemData::resource_writes(),
+            resource_reads,
+            resource_writes,
+            resource_concurrent_writes,
+            event_channels: S::SystemData::event_channels(),
             component_reads: S::SystemData::component_reads(),
             component_writes: S::SystemData::component_writes(),
+            archetype_accesses: S::SystemData::archetype_accesses(),
+            restrict_random_access: false,
             data: None,
             inner,
             name,
@@ -104,6 +163,27 @@ impl<S: System + 'static> CachedSystem<S> {
     }
 }
 
+/// Treats resources which are both read and concurrently written by the same
+/// system as exclusively written, e.g. for a system which has both an
+/// `EventReader<E>` and a `Trigger<E>`. This prevents both readers and
+/// concurrent writers of the resource from running alongside the system.
+pub(crate) fn merge_concurrent_reads(
+    reads: &mut Vec<ResourceId>,
+    writes: &mut Vec<ResourceId>,
+    concurrent_writes: &mut Vec<ResourceId>,
+) {
+    let mut merged: Vec<ResourceId> = vec![];
+    for resource in concurrent_writes.iter() {
+        if reads.contains(resource) && !writes.contains(resource) && !merged.contains(resource) {
+            merged.push(*resource);
+        }
+    }
+
+    reads.retain(|resource| !merged.contains(resource));
+    concurrent_writes.retain(|resource| !merged.contains(resource));
+    writes.extend(merged);
+}
+
 impl<S: System> RawSystem for CachedSystem<S> {
     fn id(&self) -> SystemId {
         self.id
@@ -121,6 +201,14 @@ impl<S: System> RawSystem for CachedSystem<S> {
         &self.resource_writes
     }
 
+    fn resource_concurrent_writes(&self) -> &[ResourceId] {
+        &self.resource_concurrent_writes
+    }
+
+    fn event_channels(&self) -> &[EventChannelHooks] {
+        &self.event_channels
+    }
+
     fn component_reads(&self) -> &[ComponentTypeId] {
         &self.component_reads
     }
@@ -129,9 +217,32 @@ impl<S: System> RawSystem for CachedSystem<S> {
         &self.component_writes
     }
 
+    fn archetype_accesses(&self) -> Vec<ArchetypeAccess> {
+        self.archetype_accesses.clone()
+    }
+
+    fn restrict_random_access(&mut self) {
+        self.restrict_random_access = true;
+    }
+
     fn init(&mut self, resources: &mut Resources, ctx: SystemCtx, world: &World) {
         let mut data = unsafe { S::SystemData::load_from_resources(resources, ctx, world) };
-        data.init(resources, &self.component_reads, &self.component_writes);
+        if self.restrict_random_access {
+            let unrestricted = self
+                .archetype_accesses
+                .iter()
+                .filter(|access| access.is_unrestricted());
+            let reads: Vec<_> = unrestricted
+                .clone()
+                .flat_map(|access| access.reads.iter().copied())
+                .collect();
+            let writes: Vec<_> = unrestricted
+                .flat_map(|access| access.writes.iter().copied())
+                .collect();
+            data.init(resources, &reads, &writes);
+        } else {
+            data.init(resources, &self.component_reads, &self.component_writes);
+        }
         self.data = Some(data);
     }
 
@@ -151,7 +262,10 @@ pub struct SystemCtx {
     pub(crate) sender: Sender<TaskMessage>,
     /// ID of this system.
     pub(crate) id: SystemId,
-    pub(crate) bump: Arc<ThreadLocal<Bump>>,
+    /// Bump allocators used to allocate triggered events.
+    pub(crate) bump: Arc<BumpArenas>,
+    /// Tick of the current dispatch. See `Scheduler::tick`.
+    pub(crate) tick: Arc<AtomicU64>,
 }
 
 /// A system data type. This could include queries, event triggers, `PreparedWorld`, resource
@@ -184,12 +298,47 @@ pub trait SystemData<'a>: Send + Sync + Sized + 'a {
     ) {
     }
 
+    /// Checks that this `SystemData` is valid, panicking otherwise.
+    /// This function is called when the system is created,
+    /// i.e. when it is added to a `SchedulerBuilder`.
+    ///
+    /// The default implementation of this function is a no-op.
+    fn validate() {}
+
     fn resource_reads() -> Vec<ResourceId>;
     fn resource_writes() -> Vec<ResourceId>;
 
+    /// Returns the internally synchronized resources accessed
+    /// concurrently by this `SystemData`. See `Concurrent`.
+    ///
+    /// The default implementation of this function returns an empty vector.
+    fn resource_concurrent_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    /// Returns the event channels read by this `SystemData`. See `EventReader`.
+    ///
+    /// The default implementation of this function returns an empty vector.
+    fn event_channels() -> Vec<EventChannelHooks> {
+        vec![]
+    }
+
     fn component_reads() -> Vec<ComponentTypeId>;
     fn component_writes() -> Vec<ComponentTypeId>;
 
+    /// Returns the component accesses of this `SystemData` along with
+    /// the archetypes they are restricted to. This is used for archetype-level
+    /// conflict detection. See `SchedulerBuilder::set_archetype_conflicts`.
+    ///
+    /// The default implementation of this function returns a single access
+    /// to `component_reads()` and `component_writes()` on all archetypes.
+    fn archetype_accesses() -> Vec<ArchetypeAccess> {
+        vec![ArchetypeAccess::new(
+            Self::component_reads(),
+            Self::component_writes(),
+        )]
+    }
+
     /// Prepares this `SystemData`, returning `Self::Output`
     /// to pass to a system.
     ///
@@ -241,17 +390,23 @@ impl<'a> SystemDataOutput<'a> for () {
 }
 
 /// Specifies a read requirement for a resource.
+///
+/// The optional key type `K` selects one of several independent
+/// instances of the resource type; see `resource_id_for_keyed`.
 // Safety: this contains a raw pointer which must remain valid.
-pub struct Read<T>
+pub struct Read<T, K = ()>
 where
     T: Resource,
+    K: 'static,
 {
     ptr: *const T,
+    _phantom: PhantomData<fn() -> K>,
 }
 
-impl<T> Deref for Read<T>
+impl<T, K> Deref for Read<T, K>
 where
     T: Resource,
+    K: 'static,
 {
     type Target = T;
 
@@ -261,15 +416,118 @@ where
 }
 
 // Safety: raw pointers are valid as per the scheduler guarantees.
-unsafe impl<T: Send + Resource> Send for Read<T> {}
-unsafe impl<T: Send + Sync + Resource> Sync for Read<T> {}
+unsafe impl<T: Send + Resource, K: 'static> Send for Read<T, K> {}
+unsafe impl<T: Send + Sync + Resource, K: 'static> Sync for Read<T, K> {}
 
-impl<'a, T> SystemData<'a> for Read<T>
+impl<'a, T, K> SystemData<'a> for Read<T, K>
 where
     T: Resource + TryDefault,
+    K: 'static,
 {
     type Output = &'a mut Self;
 
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        if let Some(default) = T::try_default() {
+            resources.insert_if_absent_keyed::<T, K>(default);
+        }
+
+        Self {
+            ptr: resources.get_unchecked(resource_id_for_keyed::<T, K>()) as *const T,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![resource_id_for_keyed::<T, K>()]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self
+    }
+}
+
+impl<'a, T, K> SystemDataOutput<'a> for &'a mut Read<T, K>
+where
+    T: Resource + TryDefault,
+    K: 'static,
+{
+    type SystemData = Read<T, K>;
+}
+
+impl<T, K> MacroData for &'static Read<T, K>
+where
+    T: Resource + TryDefault,
+    K: 'static,
+{
+    type SystemData = Read<T, K>;
+}
+
+/// Specifies a read requirement for a resource, additionally
+/// tracking whether the resource changed since the system last ran.
+///
+/// A resource is considered changed when it was inserted into the
+/// `Resources` or mutably dereferenced through a `Write`.
+// Safety: this contains raw pointers which must remain valid.
+pub struct ChangedRead<T>
+where
+    T: Resource,
+{
+    ptr: *const T,
+    tracker: ChangeTracker,
+    /// Value of the change counter when the system last ran.
+    last_run: u64,
+    /// Whether the resource changed between the previous run and this one.
+    changed: bool,
+}
+
+impl<T> ChangedRead<T>
+where
+    T: Resource,
+{
+    /// Returns whether the resource changed since the last
+    /// time this system ran. On the first run, this returns `true`.
+    pub fn is_changed(&self) -> bool {
+        self.changed
+    }
+}
+
+impl<T> Deref for ChangedRead<T>
+where
+    T: Resource,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.ptr }
+    }
+}
+
+// Safety: raw pointers are valid as per the scheduler guarantees.
+unsafe impl<T: Send + Resource> Send for ChangedRead<T> {}
+unsafe impl<T: Send + Sync + Resource> Sync for ChangedRead<T> {}
+
+impl<'a, T> SystemData<'a> for ChangedRead<T>
+where
+    T: Resource + TryDefault,
+{
+    type Output = &'a Self;
+
     unsafe fn load_from_resources(
         resources: &mut Resources,
         _ctx: SystemCtx,
@@ -279,8 +537,12 @@ where
             resources.insert_if_absent(default);
         }
 
+        let id = resource_id_for::<T>();
         Self {
-            ptr: resources.get_unchecked(resource_id_for::<T>()) as *const T,
+            ptr: resources.get_unchecked(id) as *const T,
+            tracker: resources.change_tracker(id),
+            last_run: 0,
+            changed: false,
         }
     }
 
@@ -301,29 +563,148 @@ where
     }
 
     fn before_execution(&'a mut self) -> Self::Output {
+        // Safety: no system writing to this resource runs
+        // concurrently, so the change tick cannot be modified
+        // between these two loads.
+        unsafe {
+            self.changed = self.tracker.tick() > self.last_run;
+            self.last_run = self.tracker.current();
+        }
         self
     }
 }
 
-impl<'a, T> SystemDataOutput<'a> for &'a mut Read<T>
+impl<'a, T> SystemDataOutput<'a> for &'a ChangedRead<T>
 where
     T: Resource + TryDefault,
 {
-    type SystemData = Read<T>;
+    type SystemData = ChangedRead<T>;
 }
 
-/// Specifies a write requirement for a resource.
+impl<T> MacroData for &'static ChangedRead<T>
+where
+    T: Resource + TryDefault,
+{
+    type SystemData = ChangedRead<T>;
+}
+
+/// Marker trait for resources which are internally synchronized,
+/// such as atomic counters or lock-free queues.
+///
+/// Such resources may be accessed through `Concurrent`.
+pub trait ConcurrentResource: Resource {}
+
+/// Specifies a concurrent access requirement for an internally
+/// synchronized resource.
+///
+/// Systems with concurrent access to the same resource may run in parallel.
+/// Concurrent access conflicts with both `Read` and `Write` access,
+/// so systems reading a resource never observe it being modified.
 // Safety: this contains a raw pointer which must remain valid.
-pub struct Write<T>
+pub struct Concurrent<T>
+where
+    T: ConcurrentResource,
+{
+    ptr: *const T,
+}
+
+impl<T> Deref for Concurrent<T>
+where
+    T: ConcurrentResource,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.ptr }
+    }
+}
+
+// Safety: raw pointers are valid as per the scheduler guarantees.
+unsafe impl<T: Send + ConcurrentResource> Send for Concurrent<T> {}
+unsafe impl<T: Send + Sync + ConcurrentResource> Sync for Concurrent<T> {}
+
+impl<'a, T> SystemData<'a> for Concurrent<T>
+where
+    T: ConcurrentResource + TryDefault,
+{
+    type Output = &'a mut Self;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        if let Some(default) = T::try_default() {
+            resources.insert_if_absent(default);
+        }
+
+        Self {
+            ptr: resources.get_unchecked(resource_id_for::<T>()) as *const T,
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn resource_concurrent_writes() -> Vec<ResourceId> {
+        vec![resource_id_for::<T>()]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self
+    }
+}
+
+impl<'a, T> SystemDataOutput<'a> for &'a mut Concurrent<T>
+where
+    T: ConcurrentResource + TryDefault,
+{
+    type SystemData = Concurrent<T>;
+}
+
+impl<T> MacroData for &'static Concurrent<T>
+where
+    T: ConcurrentResource + TryDefault,
+{
+    type SystemData = Concurrent<T>;
+}
+
+/// Specifies a write requirement for a resource.
+///
+/// The optional key type `K` selects one of several independent
+/// instances of the resource type; see `resource_id_for_keyed`.
+///
+/// Mutably dereferencing a `Write` updates the change tick of the resource.
+// Safety: this contains raw pointers which must remain valid.
+pub struct Write<T, K = ()>
 where
     T: Resource,
+    K: 'static,
 {
     ptr: *mut T,
+    tracker: ChangeTracker,
+    /// Whether the resource has been marked as changed during the current run.
+    marked: bool,
+    _phantom: PhantomData<fn() -> K>,
 }
 
-impl<T> Deref for Write<T>
+impl<T, K> Deref for Write<T, K>
 where
     T: Resource,
+    K: 'static,
 {
     type Target = T;
 
@@ -332,22 +713,30 @@ where
     }
 }
 
-impl<T> DerefMut for Write<T>
+impl<T, K> DerefMut for Write<T, K>
 where
     T: Resource,
+    K: 'static,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
+        // Only allocate one change tick per run to avoid
+        // contention on the change counter.
+        if !self.marked {
+            self.marked = true;
+            unsafe { self.tracker.mark_changed() };
+        }
         unsafe { &mut *self.ptr }
     }
 }
 
 // Safety: raw pointers are valid as per the scheduler guarantees.
-unsafe impl<T: Send + Resource> Send for Write<T> {}
-unsafe impl<T: Send + Sync + Resource> Sync for Write<T> {}
+unsafe impl<T: Send + Resource, K: 'static> Send for Write<T, K> {}
+unsafe impl<T: Send + Sync + Resource, K: 'static> Sync for Write<T, K> {}
 
-impl<'a, T> SystemData<'a> for Write<T>
+impl<'a, T, K> SystemData<'a> for Write<T, K>
 where
     T: Resource + TryDefault,
+    K: 'static,
 {
     type Output = &'a mut Self;
 
@@ -357,11 +746,15 @@ where
         _world: &World,
     ) -> Self {
         if let Some(default) = T::try_default() {
-            resources.insert_if_absent(default);
+            resources.insert_if_absent_keyed::<T, K>(default);
         }
 
+        let id = resource_id_for_keyed::<T, K>();
         Self {
-            ptr: resources.get_mut_unchecked(resource_id_for::<T>()) as *mut T,
+            ptr: resources.get_mut_unchecked(id) as *mut T,
+            tracker: resources.change_tracker(id),
+            marked: false,
+            _phantom: PhantomData,
         }
     }
 
@@ -372,7 +765,7 @@ where
         _component_writes: &[ComponentTypeId],
     ) {
         if let Some(default) = T::try_default() {
-            resources.insert_if_absent(default);
+            resources.insert_if_absent_keyed::<T, K>(default);
         }
     }
 
@@ -381,7 +774,7 @@ where
     }
 
     fn resource_writes() -> Vec<ResourceId> {
-        vec![resource_id_for::<T>()]
+        vec![resource_id_for_keyed::<T, K>()]
     }
 
     fn component_reads() -> Vec<ComponentTypeId> {
@@ -393,15 +786,105 @@ where
     }
 
     fn before_execution(&'a mut self) -> Self::Output {
+        self.marked = false;
         self
     }
 }
 
-impl<'a, T> SystemDataOutput<'a> for &'a mut Write<T>
+impl<'a, T, K> SystemDataOutput<'a> for &'a mut Write<T, K>
+where
+    T: Resource + TryDefault,
+    K: 'static,
+{
+    type SystemData = Write<T, K>;
+}
+
+impl<T, K> MacroData for &'static mut Write<T, K>
 where
     T: Resource + TryDefault,
+    K: 'static,
+{
+    type SystemData = Write<T, K>;
+}
+
+/// System-local state, owned by the system instance rather
+/// than stored in the `Resources`.
+///
+/// This can be used by systems to keep state between runs,
+/// such as frame counters or scratch buffers. The value is initialized
+/// using `Default` and accesses no resources, so it never causes conflicts.
+#[derive(Default)]
+pub struct Local<T>(T)
+where
+    T: Default + Send + Sync + 'static;
+
+impl<T> Deref for Local<T>
+where
+    T: Default + Send + Sync + 'static,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Local<T>
+where
+    T: Default + Send + Sync + 'static,
 {
-    type SystemData = Write<T>;
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<'a, T> SystemData<'a> for Local<T>
+where
+    T: Default + Send + Sync + 'static,
+{
+    type Output = &'a mut Self;
+
+    unsafe fn load_from_resources(
+        _resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        Self(T::default())
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self
+    }
+}
+
+impl<'a, T> SystemDataOutput<'a> for &'a mut Local<T>
+where
+    T: Default + Send + Sync + 'static,
+{
+    type SystemData = Local<T>;
+}
+
+impl<T> MacroData for &'static mut Local<T>
+where
+    T: Default + Send + Sync + 'static,
+{
+    type SystemData = Local<T>;
 }
 
 // `system` macro implementation details.
@@ -431,6 +914,10 @@ macro_rules! impl_data {
                 $(self.$idx.init(resources, component_reads, component_writes); )*
             }
 
+            fn validate() {
+                $($ty::validate();)*
+            }
+
             fn resource_reads() -> Vec<ResourceId> {
                 let mut res = vec![];
                 $(
@@ -447,6 +934,22 @@ macro_rules! impl_data {
                 res
             }
 
+            fn resource_concurrent_writes() -> Vec<ResourceId> {
+                let mut res = vec![];
+                $(
+                    res.append(&mut $ty::resource_concurrent_writes());
+                )*
+                res
+            }
+
+            fn event_channels() -> Vec<EventChannelHooks> {
+                let mut res = vec![];
+                $(
+                    res.append(&mut $ty::event_channels());
+                )*
+                res
+            }
+
             fn component_reads() -> Vec<ComponentTypeId> {
                 let mut res = vec![];
                 $(
@@ -463,6 +966,14 @@ macro_rules! impl_data {
                 res
             }
 
+            fn archetype_accesses() -> Vec<ArchetypeAccess> {
+                let mut res = vec![];
+                $(
+                    res.append(&mut $ty::archetype_accesses());
+                )*
+                res
+            }
+
             unsafe fn load_from_resources(resources: &mut Resources, ctx: SystemCtx, world: &World) -> Self {
                 ($($ty::load_from_resources(resources, ctx.clone(), world) ,)*)
             }
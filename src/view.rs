@@ -0,0 +1,173 @@
+//! Additional Legion views for use in a `Query`.
+
+use crate::filter::ArchetypeElement;
+use legion::borrow::{Ref, RefIter};
+use legion::filter::{EntityFilterTuple, Passthrough};
+use legion::query::{DefaultFilter, Read, ReadOnly, Tagged, View, ViewElement, Write};
+use legion::storage::{
+    ArchetypeData, Component, ComponentStorage, ComponentTypeId, Tag, TagTypeId,
+};
+use std::any::TypeId;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+/// Reads a component of type `T` if the entity has it.
+///
+/// Unlike `Read`, this does not restrict which entities
+/// are matched by the query. Entities without `T` yield `None`.
+#[derive(Debug)]
+pub struct TryRead<T>(PhantomData<T>);
+
+impl<T> ViewElement for TryRead<T> {
+    type Component = T;
+}
+
+impl<T> ReadOnly for TryRead<T> {}
+
+impl<T> DefaultFilter for TryRead<T>
+where
+    T: Component,
+{
+    type Filter = EntityFilterTuple<Passthrough, Passthrough, Passthrough>;
+
+    fn filter() -> Self::Filter {
+        EntityFilterTuple::new(Passthrough, Passthrough, Passthrough)
+    }
+}
+
+impl<'a, T> View<'a> for TryRead<T>
+where
+    T: Component,
+{
+    type Iter = TryRefIter<'a, T>;
+
+    fn fetch(
+        _archetype: &'a ArchetypeData,
+        chunk: &'a ComponentStorage,
+        _index: usize,
+    ) -> Self::Iter {
+        match chunk.components(ComponentTypeId::of::<T>()) {
+            Some(components) => {
+                let (borrow, slice) = unsafe { components.data_slice::<T>().deconstruct() };
+                TryRefIter::Present(RefIter::new(borrow, slice.iter()))
+            }
+            None => TryRefIter::Missing(0..chunk.len()),
+        }
+    }
+
+    fn validate() -> bool {
+        true
+    }
+
+    fn reads<D: Component>() -> bool {
+        TypeId::of::<T>() == TypeId::of::<D>()
+    }
+
+    fn writes<D: Component>() -> bool {
+        false
+    }
+
+    fn read_types() -> Vec<ComponentTypeId> {
+        // Declared even though the component may be absent,
+        // so conflicting writers are not scheduled concurrently.
+        vec![ComponentTypeId::of::<T>()]
+    }
+
+    fn write_types() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+}
+
+/// Iterator over the optional components of a chunk, used by `TryRead`.
+pub enum TryRefIter<'a, T: Component> {
+    /// The chunk contains the component.
+    Present(RefIter<'a, T, std::slice::Iter<'a, T>>),
+    /// The chunk does not contain the component; yields `None`
+    /// once per entity.
+    Missing(Range<usize>),
+}
+
+impl<'a, T> Iterator for TryRefIter<'a, T>
+where
+    T: Component,
+{
+    type Item = Option<Ref<'a, T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            TryRefIter::Present(iter) => iter.next().map(Some),
+            TryRefIter::Missing(range) => range.next().map(|_| None),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            TryRefIter::Present(iter) => iter.size_hint(),
+            TryRefIter::Missing(range) => range.size_hint(),
+        }
+    }
+}
+
+/// Components and tags which all entities matched by a view have.
+///
+/// This is used for archetype-level conflict detection. Views for which
+/// this is not specialized conservatively require nothing.
+pub trait RequiredElements {
+    fn required() -> Vec<ArchetypeElement>;
+}
+
+impl<V> RequiredElements for V {
+    default fn required() -> Vec<ArchetypeElement> {
+        vec![]
+    }
+}
+
+impl<T> RequiredElements for Read<T>
+where
+    T: Component,
+{
+    fn required() -> Vec<ArchetypeElement> {
+        vec![ArchetypeElement::Component(ComponentTypeId::of::<T>())]
+    }
+}
+
+impl<T> RequiredElements for Write<T>
+where
+    T: Component,
+{
+    fn required() -> Vec<ArchetypeElement> {
+        vec![ArchetypeElement::Component(ComponentTypeId::of::<T>())]
+    }
+}
+
+impl<T> RequiredElements for Tagged<T>
+where
+    T: Tag,
+{
+    fn required() -> Vec<ArchetypeElement> {
+        vec![ArchetypeElement::Tag(TagTypeId::of::<T>())]
+    }
+}
+
+macro_rules! impl_required_tuple {
+    ($($ty:ident),*) => {
+        impl<$($ty),*> RequiredElements for ($($ty,)*) {
+            fn required() -> Vec<ArchetypeElement> {
+                let mut res = vec![];
+                $(
+                    res.append(&mut $ty::required());
+                )*
+                res
+            }
+        }
+    };
+}
+
+impl_required_tuple!(A);
+impl_required_tuple!(A, B);
+impl_required_tuple!(A, B, C);
+impl_required_tuple!(A, B, C, D);
+impl_required_tuple!(A, B, C, D, E);
+impl_required_tuple!(A, B, C, D, E, F);
+impl_required_tuple!(A, B, C, D, E, F, G);
+impl_required_tuple!(A, B, C, D, E, F, G, H);
@@ -2,16 +2,26 @@
 extern crate tonks;
 
 use legion::entity::Entity;
-use legion::query::Read;
+use legion::query::{Read, Write};
 use legion::world::World;
+use std::sync::Mutex;
 use tonks::{PreparedWorld, QueryAccessor, Resources, SchedulerBuilder};
 
 #[derive(Clone, Copy)]
 struct Age(u32);
 
+#[derive(Clone, Copy)]
+struct Height(f32);
+
 #[derive(Resource)]
 struct E(Entity);
 
+#[derive(Resource)]
+struct Others {
+    without_age: Entity,
+    dead: Entity,
+}
+
 #[test]
 fn basic() {
     #[system]
@@ -30,3 +40,80 @@ fn basic() {
 
     scheduler.execute(&mut world);
 }
+
+#[test]
+fn find_validates_view() {
+    #[system]
+    fn sys(accessor: &QueryAccessor<Read<Age>>, others: &Others) {
+        assert!(accessor.find(others.without_age).is_none());
+        assert!(accessor.find(others.dead).is_none());
+    }
+
+    let mut world = World::new();
+    let without_age = world.insert((), [(Height(1.5),)].iter().copied())[0];
+    let dead = world.insert((), [(Age(30),)].iter().copied())[0];
+    world.delete(dead);
+
+    let mut resources = Resources::new();
+    resources.insert(Others { without_age, dead });
+
+    let mut scheduler = SchedulerBuilder::new().with(sys).build(resources);
+
+    scheduler.execute(&mut world);
+}
+
+/// Entities to look up, and whether they should be found.
+#[derive(Resource)]
+struct Targets(Mutex<Vec<(Entity, bool)>>);
+
+#[test]
+fn archetype_created_after_build() {
+    #[system]
+    fn sys(accessor: &QueryAccessor<Read<Age>>, targets: &Targets) {
+        for (entity, found) in targets.0.lock().unwrap().iter() {
+            assert_eq!(accessor.find(*entity).is_some(), *found);
+        }
+    }
+
+    let mut world = World::new();
+    let old = world.insert((), [(Age(1),)].iter().copied())[0];
+
+    let mut resources = Resources::new();
+    resources.insert(Targets(Mutex::new(vec![(old, true)])));
+
+    let mut scheduler = SchedulerBuilder::new().with(sys).build(resources);
+    scheduler.execute(&mut world);
+
+    // Neither archetype existed when the system first ran.
+    let new = world.insert((), [(Age(2), Height(1.0))].iter().copied())[0];
+    let without_age = world.insert((), [(Height(2.0), 0u8)].iter().copied())[0];
+    scheduler
+        .resources()
+        .get::<Targets>()
+        .0
+        .lock()
+        .unwrap()
+        .extend(vec![(new, true), (without_age, false)]);
+
+    scheduler.execute(&mut world);
+}
+
+#[test]
+fn get_component_mut() {
+    #[system]
+    fn sys(accessor: &QueryAccessor<Write<Age>>, world: &mut PreparedWorld, e: &E) {
+        let accessor = accessor.find(e.0).unwrap();
+        accessor.get_component_mut::<Age>(world).unwrap().0 += 1;
+    }
+
+    let mut world = World::new();
+    let entity = world.insert((), [(Age(16),)].iter().copied())[0];
+
+    let mut resources = Resources::new();
+    resources.insert(E(entity));
+
+    let mut scheduler = SchedulerBuilder::new().with(sys).build(resources);
+
+    scheduler.execute(&mut world);
+    assert_eq!(world.get_component::<Age>(entity).unwrap().0, 17);
+}
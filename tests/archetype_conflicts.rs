@@ -0,0 +1,63 @@
+//! Testing of archetype-level conflict detection.
+
+#[macro_use]
+extern crate tonks;
+
+use legion::query::Write;
+use legion::world::World;
+use tonks::{PreparedWorld, Query, Resources, SchedulerBuilder, With, Without};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Position(f32);
+
+#[derive(Clone, Copy)]
+struct Player;
+
+#[derive(Clone, Copy)]
+struct Projectile;
+
+#[system]
+fn move_players(query: &mut Query<Write<Position>, With<Player>>, world: &mut PreparedWorld) {
+    for mut position in query.iter(world) {
+        position.0 += 1.0;
+    }
+}
+
+#[system]
+fn move_others(query: &mut Query<Write<Position>, Without<Player>>, world: &mut PreparedWorld) {
+    for mut position in query.iter(world) {
+        position.0 += 10.0;
+    }
+}
+
+#[test]
+fn run_disjoint() {
+    let mut world = World::new();
+    let player = world.insert((), [(Position(0.0), Player)].iter().copied())[0];
+    let projectile = world.insert((), [(Position(0.0), Projectile)].iter().copied())[0];
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with_archetype_conflicts(true)
+        .with(move_players)
+        .with(move_others)
+        .build(Resources::new());
+
+    scheduler.execute(&mut world);
+
+    assert_eq!(
+        *world.get_component::<Position>(player).unwrap(),
+        Position(1.0)
+    );
+    assert_eq!(
+        *world.get_component::<Position>(projectile).unwrap(),
+        Position(10.0)
+    );
+}
+
+#[test]
+#[should_panic(expected = "before systems are added")]
+fn set_after_systems() {
+    let _ = SchedulerBuilder::new()
+        .with(move_players)
+        .with_archetype_conflicts(true);
+}
@@ -0,0 +1,156 @@
+//! Testing of events triggered by event handlers.
+
+use legion::world::World;
+use std::thread;
+use std::time::Duration;
+use tonks::{EventHandler, EventsBuilder, Resources, System, SystemData, Trigger, Write};
+
+struct Ping(u32);
+struct Pong(u32);
+
+#[derive(Default)]
+struct Log(Vec<(&'static str, u32)>);
+
+struct Start;
+
+impl System for Start {
+    type SystemData = Trigger<Ping>;
+
+    fn run(&mut self, trigger: <Self::SystemData as SystemData>::Output) {
+        trigger.trigger(Ping(4));
+    }
+}
+
+/// Answers each ping with a pong with one less remaining bounce.
+struct PingHandler;
+
+impl EventHandler<Ping> for PingHandler {
+    type HandlerData = (Trigger<Pong>, Write<Log>);
+
+    fn handle(
+        &mut self,
+        event: &Ping,
+        (trigger, log): &mut <Self::HandlerData as SystemData>::Output,
+    ) {
+        log.0.push(("ping", event.0));
+        if event.0 > 0 {
+            trigger.trigger(Pong(event.0 - 1));
+        }
+    }
+}
+
+/// Answers each pong with a ping, forever.
+struct PongHandler;
+
+impl EventHandler<Pong> for PongHandler {
+    type HandlerData = (Trigger<Ping>, Write<Log>);
+
+    fn handle(
+        &mut self,
+        event: &Pong,
+        (trigger, log): &mut <Self::HandlerData as SystemData>::Output,
+    ) {
+        log.0.push(("pong", event.0));
+        trigger.trigger(Ping(event.0));
+    }
+}
+
+#[test]
+fn handled_within_tick() {
+    let mut scheduler = EventsBuilder::new()
+        .with(PingHandler)
+        .with(PongHandler)
+        .finish()
+        .with(Start)
+        .build(Resources::new());
+
+    scheduler.execute(&mut World::new());
+
+    assert_eq!(
+        scheduler.resources().get::<Log>().0,
+        vec![
+            ("ping", 4),
+            ("pong", 3),
+            ("ping", 3),
+            ("pong", 2),
+            ("ping", 2),
+            ("pong", 1),
+            ("ping", 1),
+            ("pong", 0),
+            ("ping", 0),
+        ]
+    );
+}
+
+#[test]
+#[should_panic(expected = "event cascade exceeded the maximum depth of 3: \
+                cascade::Ping -> cascade::Pong -> cascade::Ping -> cascade::Pong -> cascade::Ping")]
+fn depth_limit() {
+    let mut scheduler = EventsBuilder::new()
+        .with(PingHandler)
+        .with(PongHandler)
+        .with_max_cascade_depth(3)
+        .finish()
+        .with(Start)
+        .build(Resources::new());
+
+    scheduler.execute(&mut World::new());
+}
+
+struct Slow;
+
+#[derive(Default)]
+struct SlowHandled(bool);
+
+struct StartBoth;
+
+impl System for StartBoth {
+    type SystemData = (Trigger<Ping>, Trigger<Slow>);
+
+    fn run(&mut self, (pings, slows): <Self::SystemData as SystemData>::Output) {
+        pings.trigger(Ping(4));
+        slows.trigger(Slow);
+    }
+}
+
+/// Takes long enough that the cascade exceeds its limit while this runs.
+struct SlowHandler;
+
+impl EventHandler<Slow> for SlowHandler {
+    type HandlerData = Write<SlowHandled>;
+
+    fn handle(&mut self, _event: &Slow, handled: &mut <Self::HandlerData as SystemData>::Output) {
+        thread::sleep(Duration::from_millis(100));
+        handled.0 = true;
+    }
+}
+
+#[test]
+fn depth_limit_error() {
+    let mut scheduler = EventsBuilder::new()
+        .with(PingHandler)
+        .with(PongHandler)
+        .with(SlowHandler)
+        .with_max_cascade_depth(3)
+        .finish()
+        .with(StartBoth)
+        .build(Resources::new());
+
+    let err = scheduler.try_execute(&mut World::new()).unwrap_err();
+    assert_eq!(err.max_depth, 3);
+    assert_eq!(
+        err.chain,
+        vec![
+            "cascade::Ping",
+            "cascade::Pong",
+            "cascade::Ping",
+            "cascade::Pong",
+            "cascade::Ping"
+        ]
+    );
+
+    // Handlers which were running when the cascade was
+    // cut off have completed before the error is returned.
+    assert!(scheduler.resources().get::<SlowHandled>().0);
+    assert_eq!(scheduler.resources().get::<Log>().0.len(), 4);
+}
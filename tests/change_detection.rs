@@ -0,0 +1,102 @@
+//! Testing of resource change detection.
+
+use legion::world::World;
+use tonks::{
+    resource_id_for, ChangedRead, Read, Resources, SchedulerBuilder, System, SystemData, Write,
+};
+
+#[derive(Default)]
+struct Counter(u32);
+
+#[derive(Default)]
+struct Observed(Vec<bool>);
+
+/// Modifies the counter on every other run.
+struct Modifier {
+    runs: u32,
+}
+
+impl System for Modifier {
+    type SystemData = Write<Counter>;
+
+    fn run(&mut self, counter: <Self::SystemData as SystemData>::Output) {
+        if self.runs % 2 == 0 {
+            counter.0 += 1;
+        } else {
+            // Immutable access does not mark the resource as changed.
+            assert!(counter.0 > 0);
+        }
+        self.runs += 1;
+    }
+}
+
+struct Observer;
+
+impl System for Observer {
+    type SystemData = (ChangedRead<Counter>, Write<Observed>);
+
+    fn run(&mut self, (counter, observed): <Self::SystemData as SystemData>::Output) {
+        observed.0.push(counter.is_changed());
+    }
+}
+
+struct Reader;
+
+impl System for Reader {
+    type SystemData = Read<Counter>;
+
+    fn run(&mut self, _counter: <Self::SystemData as SystemData>::Output) {}
+}
+
+#[test]
+fn basic() {
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Modifier { runs: 0 })
+        .with(Reader)
+        .with(Observer)
+        .build(Resources::new());
+
+    for _ in 0..6 {
+        scheduler.execute(&mut World::new());
+    }
+
+    assert_eq!(
+        scheduler.resources().get::<Observed>().0,
+        vec![true, false, true, false, true, false]
+    );
+}
+
+#[test]
+fn insert_marks_changed() {
+    let mut resources = Resources::new();
+    resources.insert(Counter(5));
+
+    let mut scheduler = SchedulerBuilder::new().with(Observer).build(resources);
+
+    for _ in 0..3 {
+        scheduler.execute(&mut World::new());
+    }
+
+    assert_eq!(
+        scheduler.resources().get::<Observed>().0,
+        vec![true, false, false]
+    );
+}
+
+#[test]
+fn change_ticks() {
+    let mut resources = Resources::new();
+    let id = resource_id_for::<Counter>();
+    assert_eq!(resources.change_tick(id), 0);
+
+    resources.insert(Counter(0));
+    let inserted = resources.change_tick(id);
+    assert!(inserted > 0);
+
+    assert_eq!(resources.get::<Counter>().0, 0);
+    assert_eq!(resources.change_tick(id), inserted);
+
+    resources.get_mut::<Counter>().0 += 1;
+    assert!(resources.change_tick(id) > inserted);
+    assert_eq!(resources.change_tick(id), resources.current_tick());
+}
@@ -0,0 +1,80 @@
+//! Testing of concurrent access to internally synchronized resources.
+
+#[macro_use]
+extern crate tonks;
+
+use legion::world::World;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tonks::{
+    Concurrent, ConcurrentResource, EventsBuilder, Read, Resources, System, SystemData, Trigger,
+    Write,
+};
+
+#[derive(Default)]
+struct Metrics(AtomicUsize);
+
+impl ConcurrentResource for Metrics {}
+
+struct Count;
+
+impl System for Count {
+    type SystemData = (Concurrent<Metrics>, Trigger<()>);
+
+    fn run(&mut self, (metrics, trigger): <Self::SystemData as SystemData>::Output) {
+        metrics.0.fetch_add(1, Ordering::Relaxed);
+        trigger.trigger(());
+    }
+}
+
+/// Checks that no system is concurrently modifying `Metrics`
+/// while it is being read.
+struct Observe;
+
+impl System for Observe {
+    type SystemData = Read<Metrics>;
+
+    fn run(&mut self, metrics: <Self::SystemData as SystemData>::Output) {
+        assert_eq!(metrics.0.load(Ordering::Relaxed) % 64, 0);
+    }
+}
+
+struct Reset;
+
+impl System for Reset {
+    type SystemData = Write<Metrics>;
+
+    fn run(&mut self, metrics: <Self::SystemData as SystemData>::Output) {
+        *metrics.0.get_mut() = 0;
+    }
+}
+
+#[test]
+fn basic() {
+    #[event_handler]
+    fn handler(_event: &(), metrics: &Concurrent<Metrics>) {
+        metrics.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    let mut builder = EventsBuilder::new().with(handler).finish();
+    builder.add(Reset);
+    for _ in 0..64 {
+        builder.add(Count);
+    }
+    builder.add(Observe);
+
+    let mut scheduler = builder.build(Resources::new());
+
+    for _ in 0..10 {
+        scheduler.execute(&mut World::new());
+
+        // 64 systems and 64 handled events
+        assert_eq!(
+            scheduler
+                .resources()
+                .get::<Metrics>()
+                .0
+                .load(Ordering::Relaxed),
+            128
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! Testing of event types defined at runtime.
+
+use legion::world::World;
+use std::alloc::Layout;
+use tonks::{
+    boxed_event_id, dynamic_event_id, DynamicEventHandler, EventsBuilder, RawTrigger, Resources,
+    System, SystemData,
+};
+
+#[derive(Default)]
+struct Total(u32);
+
+#[derive(Default)]
+struct Names(Vec<String>);
+
+struct Sys;
+
+impl System for Sys {
+    type SystemData = RawTrigger;
+
+    fn run(&mut self, trigger: <Self::SystemData as SystemData>::Output) {
+        let id = dynamic_event_id("dynamic::score", Layout::new::<u32>());
+        trigger.trigger_bytes(id, &5u32.to_ne_bytes());
+        trigger.trigger_bytes(id, &10u32.to_ne_bytes());
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(bytes);
+    u32::from_ne_bytes(buf)
+}
+
+#[test]
+fn same_name_same_id() {
+    let a = dynamic_event_id("dynamic::same", Layout::new::<u64>());
+    let b = dynamic_event_id("dynamic::same", Layout::new::<u64>());
+    assert_eq!(a, b);
+    assert_ne!(a, boxed_event_id("dynamic::other"));
+}
+
+#[test]
+#[should_panic(expected = "different layouts")]
+fn layout_mismatch() {
+    dynamic_event_id("dynamic::mismatch", Layout::new::<u32>());
+    dynamic_event_id("dynamic::mismatch", Layout::new::<u64>());
+}
+
+#[test]
+#[should_panic(expected = "not padded to its alignment")]
+fn unpadded_layout() {
+    dynamic_event_id("dynamic::unpadded", Layout::from_size_align(3, 4).unwrap());
+}
+
+#[test]
+fn bytes_and_boxed() {
+    let score = dynamic_event_id("dynamic::score", Layout::new::<u32>());
+    let named = boxed_event_id("dynamic::named");
+
+    let score_handler = DynamicEventHandler::new(score, "score_handler", |events, resources, _| {
+        let total = resources.get_mut::<Total>();
+        for event in events.iter() {
+            total.0 += read_u32(event.bytes());
+        }
+    })
+    .with_write::<Total>();
+
+    // Converts names to scores, cascading into the score handler.
+    let named_handler =
+        DynamicEventHandler::new(named, "named_handler", move |events, resources, trigger| {
+            let names = resources.get_mut::<Names>();
+            for event in events.iter() {
+                let name = event.payload().downcast_ref::<String>().unwrap();
+                names.0.push(name.clone());
+                trigger.trigger_bytes(score, &(name.len() as u32).to_ne_bytes());
+            }
+        })
+        .with_write::<Names>();
+
+    let mut resources = Resources::new();
+    resources.insert(Total::default());
+    resources.insert(Names::default());
+
+    let mut events = EventsBuilder::new();
+    events.add_boxed(Box::new(score_handler));
+    events.add_boxed(Box::new(named_handler));
+    let mut scheduler = events.finish().with(Sys).build(resources);
+
+    scheduler.trigger_boxed(named, Box::new(String::from("tonks")));
+    scheduler.trigger_bytes(score, &100u32.to_ne_bytes());
+    scheduler.execute(&mut World::new());
+
+    assert_eq!(scheduler.resources().get::<Total>().0, 120);
+    assert_eq!(scheduler.resources().get::<Names>().0, vec!["tonks"]);
+}
+
+#[test]
+fn stop_propagation() {
+    let id = dynamic_event_id("dynamic::stopped", Layout::new::<u32>());
+
+    let first = DynamicEventHandler::new(id, "first", |events, _, _| {
+        events
+            .iter()
+            .filter(|event| read_u32(event.bytes()) > 1)
+            .for_each(|event| event.stop_propagation());
+    })
+    .with_priority(1)
+    .with_stops_propagation();
+    let second = DynamicEventHandler::new(id, "second", |events, resources, _| {
+        resources.get_mut::<Total>().0 += events.iter().count() as u32;
+    })
+    .with_write::<Total>();
+
+    let mut resources = Resources::new();
+    resources.insert(Total::default());
+
+    let mut events = EventsBuilder::new();
+    events.add_boxed(Box::new(second));
+    events.add_boxed(Box::new(first));
+    let mut scheduler = events.finish().build(resources);
+
+    for value in 0..4u32 {
+        scheduler.trigger_bytes(id, &value.to_ne_bytes());
+    }
+    scheduler.execute(&mut World::new());
+
+    assert_eq!(scheduler.resources().get::<Total>().0, 2);
+}
+
+#[test]
+#[should_panic(expected = "does not match its layout")]
+fn wrong_size() {
+    let id = dynamic_event_id("dynamic::sized", Layout::new::<u32>());
+    let mut scheduler = EventsBuilder::new().finish().build(Resources::new());
+    scheduler.trigger_bytes(id, &[0; 2]);
+}
+
+#[test]
+#[should_panic(expected = "not a dynamic event of bytes")]
+fn boxed_as_bytes() {
+    let id = boxed_event_id("dynamic::boxed");
+    let mut scheduler = EventsBuilder::new().finish().build(Resources::new());
+    scheduler.trigger_bytes(id, &[]);
+}
@@ -0,0 +1,136 @@
+//! Testing that events are dropped exactly once.
+
+use legion::world::World;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tonks::{
+    EventHandler, EventReader, EventsBuilder, Resources, SchedulerBuilder, System, SystemData,
+    Trigger,
+};
+
+/// Event which counts how many times it has been dropped.
+struct Counted {
+    drops: Arc<AtomicUsize>,
+    data: String,
+}
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        self.drops.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+struct Sys(Arc<AtomicUsize>);
+
+impl System for Sys {
+    type SystemData = Trigger<Counted>;
+
+    fn run(&mut self, trigger: <Self::SystemData as SystemData>::Output) {
+        for _ in 0..3 {
+            trigger.trigger(Counted {
+                drops: Arc::clone(&self.0),
+                data: String::from("event"),
+            });
+        }
+    }
+}
+
+struct Handler;
+
+impl EventHandler<Counted> for Handler {
+    type HandlerData = ();
+
+    fn handle(&mut self, event: &Counted, _data: &mut <Self::HandlerData as SystemData>::Output) {
+        // Events must not have been dropped before they are handled.
+        assert_eq!(event.data, "event");
+    }
+}
+
+struct Reader;
+
+impl System for Reader {
+    type SystemData = EventReader<Counted>;
+
+    fn run(&mut self, reader: <Self::SystemData as SystemData>::Output) {
+        assert!(reader.iter().all(|event| event.data == "event"));
+    }
+}
+
+#[test]
+fn handled() {
+    let drops = Arc::new(AtomicUsize::new(0));
+
+    for &coalesce in &[true, false] {
+        drops.store(0, Ordering::SeqCst);
+
+        let mut scheduler = EventsBuilder::new()
+            .with(Handler)
+            .with_coalesce_events(coalesce)
+            .finish()
+            .with(Sys(Arc::clone(&drops)))
+            .with(Sys(Arc::clone(&drops)))
+            .build(Resources::new());
+
+        for tick in 1..=10 {
+            scheduler.execute(&mut World::new());
+            assert_eq!(drops.load(Ordering::SeqCst), tick * 6);
+        }
+    }
+}
+
+#[test]
+fn unhandled() {
+    let drops = Arc::new(AtomicUsize::new(0));
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Sys(Arc::clone(&drops)))
+        .build(Resources::new());
+
+    for tick in 1..=10 {
+        scheduler.execute(&mut World::new());
+        assert_eq!(drops.load(Ordering::SeqCst), tick * 3);
+    }
+}
+
+#[test]
+fn read() {
+    let drops = Arc::new(AtomicUsize::new(0));
+
+    let mut scheduler = EventsBuilder::new()
+        .with(Handler)
+        .finish()
+        .with(Sys(Arc::clone(&drops)))
+        .with(Reader)
+        .build(Resources::new());
+
+    // Events are kept in the event channel until the end of the next tick.
+    for tick in 1..=10 {
+        scheduler.execute(&mut World::new());
+        assert_eq!(drops.load(Ordering::SeqCst), (tick - 1) * 3);
+    }
+}
+
+#[test]
+fn manually_triggered() {
+    let drops = Arc::new(AtomicUsize::new(0));
+
+    let mut scheduler = EventsBuilder::new()
+        .with(Handler)
+        .finish()
+        .build(Resources::new());
+
+    scheduler.trigger(Counted {
+        drops: Arc::clone(&drops),
+        data: String::from("event"),
+    });
+    scheduler.execute(&mut World::new());
+    assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+    // Events which were never handled are dropped along with the scheduler.
+    scheduler.trigger(Counted {
+        drops: Arc::clone(&drops),
+        data: String::from("event"),
+    });
+    drop(scheduler);
+    assert_eq!(drops.load(Ordering::SeqCst), 2);
+}
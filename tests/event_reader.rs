@@ -0,0 +1,142 @@
+//! Testing of pull-based event consumption using `EventReader`.
+
+use legion::world::World;
+use tonks::{
+    EventHandler, EventReader, EventsBuilder, Resources, SchedulerBuilder, System, SystemData,
+    Trigger, Write,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Ev(String);
+
+#[derive(Default)]
+struct Tick(u32);
+
+#[derive(Default)]
+struct Seen(Vec<Ev>);
+
+#[derive(Default)]
+struct Handled(Vec<Ev>);
+
+struct Triggerer;
+
+impl System for Triggerer {
+    type SystemData = (Trigger<Ev>, Write<Tick>);
+
+    fn run(&mut self, (trigger, tick): <Self::SystemData as SystemData>::Output) {
+        trigger.trigger(Ev(tick.0.to_string()));
+        tick.0 += 1;
+    }
+}
+
+struct Reader;
+
+impl System for Reader {
+    type SystemData = (EventReader<Ev>, Write<Seen>);
+
+    fn run(&mut self, (reader, seen): <Self::SystemData as SystemData>::Output) {
+        seen.0.extend(reader.iter().cloned());
+    }
+}
+
+struct Handler;
+
+impl EventHandler<Ev> for Handler {
+    type HandlerData = Write<Handled>;
+
+    fn handle(&mut self, event: &Ev, handled: &mut <Self::HandlerData as SystemData>::Output) {
+        handled.0.push(event.clone());
+    }
+}
+
+fn events(range: std::ops::Range<u32>) -> Vec<Ev> {
+    range.map(|x| Ev(x.to_string())).collect()
+}
+
+#[test]
+fn same_tick() {
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Triggerer)
+        .with(Reader)
+        .build(Resources::new());
+
+    for _ in 0..3 {
+        scheduler.execute(&mut World::new());
+    }
+
+    assert_eq!(scheduler.resources().get::<Seen>().0, events(0..3));
+}
+
+#[test]
+fn previous_tick() {
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Reader)
+        .with(Triggerer)
+        .build(Resources::new());
+
+    for _ in 0..3 {
+        scheduler.execute(&mut World::new());
+    }
+
+    // Events triggered during the last tick have not yet been observed.
+    assert_eq!(scheduler.resources().get::<Seen>().0, events(0..2));
+}
+
+#[test]
+fn with_handler() {
+    let mut scheduler = EventsBuilder::new()
+        .with(Handler)
+        .finish()
+        .with(Triggerer)
+        .with(Reader)
+        .build(Resources::new());
+
+    scheduler.trigger(Ev(String::from("manual")));
+
+    for _ in 0..3 {
+        scheduler.execute(&mut World::new());
+    }
+
+    let mut expected = vec![Ev(String::from("manual"))];
+    expected.extend(events(0..3));
+
+    assert_eq!(scheduler.resources().get::<Seen>().0, expected);
+
+    let mut handled = scheduler.resources().get::<Handled>().0.clone();
+    handled.sort_by(|a, b| a.0.cmp(&b.0));
+    expected.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(handled, expected);
+}
+
+struct Relay;
+
+impl System for Relay {
+    type SystemData = (EventReader<Ev>, Trigger<Ev>);
+
+    fn run(&mut self, (reader, trigger): <Self::SystemData as SystemData>::Output) {
+        for event in reader.iter() {
+            if !event.0.ends_with('!') {
+                trigger.trigger(Ev(format!("{}!", event.0)));
+            }
+        }
+    }
+}
+
+#[test]
+fn read_and_trigger() {
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Triggerer)
+        .with(Relay)
+        .with(Reader)
+        .build(Resources::new());
+
+    for _ in 0..2 {
+        scheduler.execute(&mut World::new());
+    }
+
+    let expected: Vec<_> = ["0", "0!", "1", "1!"]
+        .iter()
+        .map(|x| Ev(x.to_string()))
+        .collect();
+    assert_eq!(scheduler.resources().get::<Seen>().0, expected);
+}
@@ -1,10 +1,12 @@
 use hashbrown::HashMap;
 use legion::world::World;
 use std::iter;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
 use tonks::{
-    resource_id_for, EventHandler, EventsBuilder, Read, Resources, System, SystemData, Trigger,
-    Write,
+    resource_id_for, EventHandler, EventsBuilder, Propagation, Read, Resources, System, SystemData,
+    Trigger, Write,
 };
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -263,3 +265,237 @@ fn recursive_trigger() {
         assert_eq!(count, 8);
     }
 }
+
+#[test]
+fn priorities_and_propagation() {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    struct Damage(u32);
+
+    #[derive(Default)]
+    struct Log(Vec<(&'static str, u32)>);
+
+    struct Trig;
+
+    impl System for Trig {
+        type SystemData = Trigger<Damage>;
+
+        fn run(&mut self, trigger: <Self::SystemData as SystemData>::Output) {
+            trigger.trigger_batched([Damage(1), Damage(10), Damage(2)].iter().copied());
+        }
+    }
+
+    struct Health;
+
+    impl EventHandler<Damage> for Health {
+        type HandlerData = Write<Log>;
+
+        fn handle(&mut self, event: &Damage, log: &mut <Self::HandlerData as SystemData>::Output) {
+            log.0.push(("health", event.0));
+        }
+    }
+
+    /// Absorbs small amounts of damage.
+    struct Shield;
+
+    impl EventHandler<Damage> for Shield {
+        type HandlerData = Write<Log>;
+
+        fn handle(
+            &mut self,
+            _event: &Damage,
+            _log: &mut <Self::HandlerData as SystemData>::Output,
+        ) {
+            unreachable!()
+        }
+
+        fn handle_propagating(
+            &mut self,
+            event: &Damage,
+            log: &mut <Self::HandlerData as SystemData>::Output,
+        ) -> Propagation {
+            log.0.push(("shield", event.0));
+            if event.0 < 5 {
+                Propagation::Stop
+            } else {
+                Propagation::Continue
+            }
+        }
+
+        fn stops_propagation(&self) -> bool {
+            true
+        }
+
+        fn priority(&self) -> i32 {
+            10
+        }
+    }
+
+    let mut scheduler = EventsBuilder::new()
+        .with(Health)
+        .with(Shield)
+        .finish()
+        .with(Trig)
+        .build(Resources::new());
+
+    scheduler.execute(&mut World::new());
+
+    assert_eq!(
+        scheduler.resources().get::<Log>().0,
+        vec![("shield", 1), ("shield", 10), ("shield", 2), ("health", 10)]
+    );
+}
+
+#[test]
+fn coalesced_batches() {
+    struct Sys;
+
+    impl System for Sys {
+        type SystemData = Trigger<Ev>;
+
+        fn run(&mut self, trigger: <Self::SystemData as SystemData>::Output) {
+            trigger.trigger(Ev(1));
+        }
+    }
+
+    #[derive(Default)]
+    struct BatchSizes(Vec<usize>);
+
+    struct Handler;
+
+    impl EventHandler<Ev> for Handler {
+        type HandlerData = Write<BatchSizes>;
+
+        fn handle(&mut self, _event: &Ev, _data: &mut <Self::HandlerData as SystemData>::Output) {
+            unreachable!()
+        }
+
+        fn handle_batch(
+            &mut self,
+            events: &[Ev],
+            sizes: <Self::HandlerData as SystemData>::Output,
+        ) {
+            assert!(events.iter().all(|event| *event == Ev(1)));
+            sizes.0.push(events.len());
+        }
+    }
+
+    for &coalesce in &[Some(true), Some(false), None] {
+        let mut events = EventsBuilder::new().with(Handler);
+        if let Some(coalesce) = coalesce {
+            events.set_coalesce_events(coalesce);
+        }
+        let mut builder = events.finish();
+
+        for _ in 0..10 {
+            builder.add(Sys);
+        }
+
+        let mut scheduler = builder.build(Resources::new());
+        scheduler.execute(&mut World::new());
+
+        let sizes = &scheduler.resources().get::<BatchSizes>().0;
+        // Coalescing is disabled by default.
+        if coalesce == Some(true) {
+            assert_eq!(sizes, &vec![10]);
+        } else {
+            assert_eq!(sizes, &vec![1; 10]);
+        }
+    }
+}
+
+/// Waits until `done` returns true, returning false if it does not within five seconds.
+fn wait_until(done: impl Fn() -> bool) -> bool {
+    let start = Instant::now();
+    while !done() {
+        if start.elapsed() > Duration::from_secs(5) {
+            return false;
+        }
+        thread::yield_now();
+    }
+    true
+}
+
+#[test]
+fn parallel_handler_stages() {
+    static ARRIVED: AtomicUsize = AtomicUsize::new(0);
+    static SYSTEM_RAN: AtomicBool = AtomicBool::new(false);
+
+    #[derive(Default)]
+    struct First {
+        met: bool,
+    }
+
+    #[derive(Default)]
+    struct Second {
+        met: bool,
+        saw_system: bool,
+    }
+
+    /// Meets `SecondHandler` in the first sub-stage.
+    struct FirstHandler;
+
+    impl EventHandler<Ev> for FirstHandler {
+        type HandlerData = Write<First>;
+
+        fn handle(&mut self, _event: &Ev, first: &mut <Self::HandlerData as SystemData>::Output) {
+            ARRIVED.fetch_add(1, Ordering::AcqRel);
+            first.met = wait_until(|| ARRIVED.load(Ordering::Acquire) == 2);
+        }
+    }
+
+    /// Meets `FirstHandler` in the first sub-stage.
+    struct SecondHandler;
+
+    impl EventHandler<Ev> for SecondHandler {
+        type HandlerData = Write<Second>;
+
+        fn handle(&mut self, _event: &Ev, second: &mut <Self::HandlerData as SystemData>::Output) {
+            ARRIVED.fetch_add(1, Ordering::AcqRel);
+            second.met = wait_until(|| ARRIVED.load(Ordering::Acquire) == 2);
+        }
+    }
+
+    /// Conflicts with `SecondHandler`, so it runs in the second sub-stage.
+    /// It only completes once `Sys` has run.
+    struct LateHandler;
+
+    impl EventHandler<Ev> for LateHandler {
+        type HandlerData = Write<Second>;
+
+        fn handle(&mut self, _event: &Ev, second: &mut <Self::HandlerData as SystemData>::Output) {
+            second.saw_system = wait_until(|| SYSTEM_RAN.load(Ordering::Acquire));
+        }
+    }
+
+    /// Writes `First`, which is released once the first sub-stage completes.
+    struct Sys;
+
+    impl System for Sys {
+        type SystemData = Write<First>;
+
+        fn run(&mut self, _first: <Self::SystemData as SystemData>::Output) {
+            SYSTEM_RAN.store(true, Ordering::Release);
+        }
+    }
+
+    let mut resources = Resources::new();
+    resources.insert(First::default());
+    resources.insert(Second::default());
+
+    let mut scheduler = EventsBuilder::new()
+        .with(FirstHandler)
+        .with(SecondHandler)
+        .with(LateHandler)
+        .finish()
+        .with(Sys)
+        .build(resources);
+
+    // Manually triggered events are handled before the systems run.
+    scheduler.trigger(Ev(0));
+    scheduler.execute(&mut World::new());
+
+    assert!(scheduler.resources().get::<First>().met);
+    let second = scheduler.resources().get::<Second>();
+    assert!(second.met);
+    assert!(second.saw_system);
+}
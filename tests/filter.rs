@@ -0,0 +1,238 @@
+//! Testing of query filters.
+
+#[macro_use]
+extern crate tonks;
+
+use legion::entity::Entity;
+use legion::query::{Read, Write};
+use legion::world::World;
+use std::cell::Cell;
+use tonks::{
+    Added, Changed, PreparedWorld, Query, Resources, Scheduler, SchedulerBuilder, TagValue, Tagged,
+    Without,
+};
+
+#[derive(Clone, Copy)]
+struct Position(f32);
+
+#[derive(Clone, Copy)]
+struct Velocity(f32);
+
+#[derive(Clone, Copy)]
+struct Static;
+
+#[derive(Clone, Copy, PartialEq)]
+struct Team(u32);
+
+#[derive(Default, Resource)]
+struct Counts(Vec<usize>);
+
+fn world() -> (World, Entity) {
+    let mut world = World::new();
+    let moving = world.insert(
+        (),
+        [
+            (Position(0.0), Velocity(1.0)),
+            (Position(1.0), Velocity(1.0)),
+        ]
+        .iter()
+        .copied(),
+    )[0];
+    world.insert((), [(Position(2.0),)].iter().copied());
+    (world, moving)
+}
+
+fn scheduler<S: tonks::System + 'static>(system: S) -> Scheduler {
+    let mut resources = Resources::new();
+    resources.insert(Counts::default());
+    SchedulerBuilder::new().with(system).build(resources)
+}
+
+#[test]
+fn changed() {
+    #[system]
+    fn sys(
+        query: &mut Query<Read<Position>, Changed<Position>>,
+        world: &mut PreparedWorld,
+        counts: &mut Counts,
+    ) {
+        counts.0.push(query.iter(world).count());
+    }
+
+    let (mut world, moving) = world();
+    let mut scheduler = scheduler(sys);
+
+    scheduler.execute(&mut world);
+    scheduler.execute(&mut world);
+
+    world.get_component_mut::<Position>(moving).unwrap().0 += 1.0;
+    scheduler.execute(&mut world);
+    scheduler.execute(&mut world);
+
+    // Change detection is per chunk, so both entities in the chunk are yielded.
+    assert_eq!(scheduler.resources().get::<Counts>().0, vec![3, 0, 2, 0]);
+}
+
+#[test]
+fn added() {
+    #[system]
+    fn sys(
+        query: &mut Query<Read<Position>, Added<Position>>,
+        world: &mut PreparedWorld,
+        counts: &mut Counts,
+    ) {
+        counts.0.push(query.iter(world).count());
+    }
+
+    let (mut world, _) = world();
+    let mut scheduler = scheduler(sys);
+
+    scheduler.execute(&mut world);
+    scheduler.execute(&mut world);
+
+    let inserted = world.insert((), [(Position(3.0),)].iter().copied())[0];
+    scheduler.execute(&mut world);
+    scheduler.execute(&mut world);
+
+    // Replacing an entity leaves the number of entities in the chunk unchanged.
+    world.delete(inserted);
+    world.insert((), [(Position(4.0),)].iter().copied());
+    scheduler.execute(&mut world);
+    scheduler.execute(&mut world);
+
+    assert_eq!(
+        scheduler.resources().get::<Counts>().0,
+        vec![3, 0, 2, 0, 2, 0]
+    );
+}
+
+#[test]
+fn added_ignores_moved_entities() {
+    #[system]
+    fn sys(
+        query: &mut Query<Read<Position>, Added<Position>>,
+        world: &mut PreparedWorld,
+        counts: &mut Counts,
+    ) {
+        counts.0.push(query.iter(world).count());
+    }
+
+    let (mut world, moving) = world();
+    let mut scheduler = scheduler(sys);
+    scheduler.execute(&mut world);
+
+    // The entity moves to a new chunk, but it already had a `Position`.
+    world.add_component(moving, Static).unwrap();
+    scheduler.execute(&mut world);
+
+    assert_eq!(scheduler.resources().get::<Counts>().0, vec![3, 0]);
+}
+
+#[test]
+#[should_panic(expected = "writes a component filtered by `Changed`")]
+fn changed_rejects_own_writes() {
+    #[system]
+    fn sys(query: &mut Query<Write<Position>, Changed<Position>>, world: &mut PreparedWorld) {
+        for mut position in query.iter(world) {
+            position.0 += 1.0;
+        }
+    }
+
+    scheduler(sys);
+}
+
+#[test]
+fn without_and_tagged() {
+    #[system]
+    fn sys(
+        dynamic: &mut Query<Read<Position>, Without<Static>>,
+        team_static: &mut Query<Read<Position>, (Tagged<Team>, Without<Velocity>)>,
+        world: &mut PreparedWorld,
+        counts: &mut Counts,
+    ) {
+        counts.0.push(dynamic.iter(world).count());
+        counts.0.push(team_static.iter(world).count());
+    }
+
+    let (mut world, _) = world();
+    world.insert((), [(Position(3.0), Static)].iter().copied());
+    world.insert((Team(1),), [(Position(4.0), Static)].iter().copied());
+    world.insert((Team(2),), [(Position(5.0), Velocity(1.0))].iter().copied());
+
+    let mut scheduler = scheduler(sys);
+    scheduler.execute(&mut world);
+
+    assert_eq!(scheduler.resources().get::<Counts>().0, vec![4, 1]);
+}
+
+#[test]
+fn tag_value() {
+    #[system]
+    fn sys(
+        query: &mut Query<Read<Position>, TagValue<Team>>,
+        world: &mut PreparedWorld,
+        counts: &mut Counts,
+    ) {
+        counts.0.push(query.iter(world).count());
+        query.set_tag_value(Team(1));
+        counts.0.push(query.iter(world).count());
+        query.set_tag_value(Team(2));
+        counts.0.push(query.iter(world).count());
+    }
+
+    let (mut world, _) = world();
+    world.insert(
+        (Team(1),),
+        [(Position(4.0),), (Position(5.0),)].iter().copied(),
+    );
+    world.insert((Team(2),), [(Position(6.0),)].iter().copied());
+
+    let mut scheduler = scheduler(sys);
+    scheduler.execute(&mut world);
+
+    assert_eq!(scheduler.resources().get::<Counts>().0, vec![0, 2, 1]);
+}
+
+#[test]
+fn cached_archetypes() {
+    #[system]
+    fn sys(
+        query: &mut Query<Read<Position>, Without<Static>>,
+        world: &mut PreparedWorld,
+        counts: &mut Counts,
+    ) {
+        let count = Cell::new(0);
+        query.for_each(world, |_| count.set(count.get() + 1));
+        counts.0.push(count.get());
+
+        // The iterators go through the same cache.
+        assert_eq!(query.iter(world).count(), count.get());
+        assert_eq!(query.iter_entities(world).count(), count.get());
+        assert_eq!(
+            query
+                .iter_chunks(world)
+                .map(|chunk| chunk.entities().len())
+                .sum::<usize>(),
+            count.get()
+        );
+    }
+
+    let (mut world, _) = world();
+    let mut scheduler = scheduler(sys);
+    scheduler.execute(&mut world);
+
+    world.insert((), [(Position(3.0), Static)].iter().copied());
+    world.insert((), [(Position(4.0), 1u8)].iter().copied());
+    scheduler.execute(&mut world);
+
+    world.insert((), [(Position(5.0),)].iter().copied());
+    scheduler.execute(&mut world);
+
+    // The cache is reset for a different world.
+    let mut other = World::new();
+    other.insert((), [(Position(0.0), Static)].iter().copied());
+    other.insert((), [(Position(1.0),)].iter().copied());
+    scheduler.execute(&mut other);
+
+    assert_eq!(scheduler.resources().get::<Counts>().0, vec![3, 4, 5, 1]);
+}
@@ -0,0 +1,146 @@
+#![cfg(feature = "serde")]
+
+use legion::world::World;
+use serde::{Deserialize, Serialize};
+use tonks::{
+    EventHandler, EventJournal, EventRegistry, EventsBuilder, Resources, Scheduler, System,
+    SystemData, Trigger, Write,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Damage(u32);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Input {
+    key: String,
+}
+
+/// Not registered, so it should not be recorded.
+struct Unregistered;
+
+#[derive(Default)]
+struct Log(Vec<String>);
+
+struct Sys(u32);
+
+impl System for Sys {
+    type SystemData = (Trigger<Damage>, Trigger<Unregistered>);
+
+    fn run(&mut self, (damage, unregistered): <Self::SystemData as SystemData>::Output) {
+        self.0 += 1;
+        damage.trigger_batched(vec![Damage(self.0), Damage(self.0 * 10)]);
+        unregistered.trigger(Unregistered);
+    }
+}
+
+struct DamageHandler;
+
+impl EventHandler<Damage> for DamageHandler {
+    type HandlerData = Write<Log>;
+
+    fn handle(&mut self, event: &Damage, log: &mut <Self::HandlerData as SystemData>::Output) {
+        log.0.push(format!("damage {}", event.0));
+    }
+}
+
+struct InputHandler;
+
+impl EventHandler<Input> for InputHandler {
+    type HandlerData = Write<Log>;
+
+    fn handle(&mut self, event: &Input, log: &mut <Self::HandlerData as SystemData>::Output) {
+        log.0.push(format!("input {}", event.key));
+    }
+}
+
+fn registry() -> EventRegistry {
+    EventRegistry::new()
+        .with::<Damage>("damage")
+        .with::<Input>("input")
+}
+
+fn events() -> EventsBuilder {
+    EventsBuilder::new().with(DamageHandler).with(InputHandler)
+}
+
+fn record() -> (Scheduler, EventJournal) {
+    let mut scheduler = events()
+        .with_event_journal(registry())
+        .finish()
+        .with(Sys(0))
+        .build(Resources::new());
+
+    let mut world = World::new();
+    scheduler.execute(&mut world);
+    scheduler.trigger(Input {
+        key: String::from("jump"),
+    });
+    scheduler.execute(&mut world);
+
+    let journal = scheduler.take_journal();
+    (scheduler, journal)
+}
+
+#[test]
+fn records_events() {
+    let (_, journal) = record();
+
+    let entries: Vec<_> = journal
+        .entries()
+        .map(|entry| (entry.event_name(), entry.tick(), entry.source().is_some()))
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            ("damage", 0, true),
+            ("input", 1, false),
+            ("damage", 1, true)
+        ]
+    );
+
+    // Sources are recorded by name, which is stable across runs.
+    assert_eq!(
+        serde_json::to_string(&journal).unwrap(),
+        concat!(
+            r#"[{"event":"damage","tick":0,"source":"journal::Sys","events":[1,10]},"#,
+            r#"{"event":"input","tick":1,"source":null,"events":[{"key":"jump"}]},"#,
+            r#"{"event":"damage","tick":1,"source":"journal::Sys","events":[2,20]}]"#,
+        )
+    );
+}
+
+#[test]
+fn replay_from_file() {
+    let (recorded, journal) = record();
+
+    let path = std::env::temp_dir().join("tonks_journal_replay.json");
+    std::fs::write(&path, serde_json::to_vec(&journal).unwrap()).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let loaded = registry()
+        .deserialize_journal(&mut serde_json::Deserializer::from_slice(&bytes))
+        .unwrap();
+    assert_eq!(loaded.len(), 3);
+
+    // The replaying scheduler has no systems, so all events are replayed.
+    let mut scheduler = events().finish().build(Resources::new());
+    scheduler.replay(loaded);
+
+    let mut world = World::new();
+    scheduler.execute(&mut world);
+    scheduler.execute(&mut world);
+
+    assert_eq!(
+        scheduler.resources().get::<Log>().0,
+        recorded.resources().get::<Log>().0
+    );
+}
+
+#[test]
+fn unknown_event() {
+    let result = registry().deserialize_journal(&mut serde_json::Deserializer::from_str(
+        r#"[{"event":"explosion","tick":0,"source":null,"events":[]}]"#,
+    ));
+    assert!(result.is_err());
+}
@@ -0,0 +1,72 @@
+//! Testing of keyed resources.
+
+#[macro_use]
+extern crate tonks;
+
+use legion::world::World;
+use tonks::{Read, Resources, SchedulerBuilder, System, SystemData, Write};
+
+#[derive(Default, Debug, PartialEq)]
+struct Timer(u32);
+
+struct Physics;
+struct Render;
+
+struct PhysicsSystem;
+
+impl System for PhysicsSystem {
+    type SystemData = Write<Timer, Physics>;
+
+    fn run(&mut self, timer: <Self::SystemData as SystemData>::Output) {
+        timer.0 += 1;
+    }
+}
+
+struct RenderSystem;
+
+impl System for RenderSystem {
+    type SystemData = (Write<Timer, Render>, Read<Timer, Physics>);
+
+    fn run(&mut self, (timer, physics): <Self::SystemData as SystemData>::Output) {
+        timer.0 += 10 * physics.0;
+    }
+}
+
+#[test]
+fn basic() {
+    let mut resources = Resources::new();
+    resources.insert(Timer(100));
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(PhysicsSystem)
+        .with(RenderSystem)
+        .build(resources);
+
+    for _ in 0..3 {
+        scheduler.execute(&mut World::new());
+    }
+
+    let resources = scheduler.resources();
+    assert_eq!(resources.get_keyed::<Timer, Physics>(), &Timer(3));
+    assert_eq!(resources.get_keyed::<Timer, Render>(), &Timer(10 + 20 + 30));
+    assert_eq!(resources.get::<Timer>(), &Timer(100));
+}
+
+#[test]
+fn macro_system() {
+    #[system]
+    fn sys(physics: &mut Write<Timer, Physics>, render: &Read<Timer, Render>) {
+        physics.0 += render.0 + 1;
+    }
+
+    let mut resources = Resources::new();
+    resources.insert_keyed::<_, Render>(Timer(5));
+
+    let mut scheduler = SchedulerBuilder::new().with(sys).build(resources);
+    scheduler.execute(&mut World::new());
+
+    assert_eq!(
+        scheduler.resources().get_keyed::<Timer, Physics>(),
+        &Timer(6)
+    );
+}
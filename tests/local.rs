@@ -0,0 +1,56 @@
+//! Testing of system-local state.
+
+#[macro_use]
+extern crate tonks;
+
+use legion::world::World;
+use tonks::{Local, Resources, SchedulerBuilder, System, SystemData, Write};
+
+#[derive(Default, Resource)]
+struct Frames(Vec<u32>);
+
+#[test]
+fn macro_system() {
+    #[system]
+    fn sys(counter: &mut Local<u32>, frames: &mut Frames) {
+        **counter += 1;
+        frames.0.push(**counter);
+    }
+
+    let mut scheduler = SchedulerBuilder::new().with(sys).build(Resources::new());
+
+    for _ in 0..3 {
+        scheduler.execute(&mut World::new());
+    }
+
+    assert_eq!(scheduler.resources().get::<Frames>().0, vec![1, 2, 3]);
+}
+
+#[test]
+fn independent_instances() {
+    #[derive(Default)]
+    struct Sums(Vec<usize>);
+
+    struct Sys;
+
+    impl System for Sys {
+        type SystemData = (Local<Vec<u32>>, Write<Sums>);
+
+        fn run(&mut self, (scratch, sums): <Self::SystemData as SystemData>::Output) {
+            scratch.push(1);
+            sums.0.push(scratch.len());
+        }
+    }
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Sys)
+        .with(Sys)
+        .build(Resources::new());
+
+    for _ in 0..2 {
+        scheduler.execute(&mut World::new());
+    }
+
+    // Each instance has its own scratch buffer.
+    assert_eq!(scheduler.resources().get::<Sums>().0, vec![1, 1, 2, 2]);
+}
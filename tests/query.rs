@@ -3,7 +3,7 @@
 use hashbrown::HashMap;
 use legion::query::Read;
 use legion::world::World;
-use tonks::{PreparedWorld, Query, Resources, SchedulerBuilder};
+use tonks::{PreparedWorld, Query, Resources, SchedulerBuilder, TryRead};
 
 #[derive(Debug)]
 struct Name(&'static str);
@@ -46,3 +46,29 @@ fn basic() {
         scheduler.execute(&mut world);
     }
 }
+
+#[test]
+fn optional() {
+    let mut world = World::new();
+
+    world.insert((), vec![(Name("Young"), Age(5))]);
+    world.insert((), vec![(Name("Ageless"),)]);
+
+    #[tonks::system]
+    fn sys(query: &mut Query<(Read<Name>, TryRead<Age>)>, world: &mut PreparedWorld) {
+        let mut ages = HashMap::new();
+
+        for (name, age) in query.iter(world) {
+            ages.insert(name.0, age.map(|age| age.0));
+        }
+
+        assert_eq!(ages["Young"], Some(5));
+        assert_eq!(ages["Ageless"], None);
+    }
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(sys)
+        .build(Resources::default());
+
+    scheduler.execute(&mut world);
+}
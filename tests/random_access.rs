@@ -1,10 +1,11 @@
+//! Testing of random component access through `PreparedWorld`.
+
 #[macro_use]
 extern crate tonks;
 
 use legion::entity::Entity;
-use legion::query::Read;
 use legion::world::World;
-use tonks::{PreparedWorld, Query, Resources, SchedulerBuilder};
+use tonks::{PreparedWorld, ReadComponent, Resources, SchedulerBuilder, WriteComponent};
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 struct Age(u32);
@@ -14,7 +15,7 @@ struct E(Entity);
 #[test]
 fn basic() {
     #[system]
-    fn sys(_query: &mut Query<Read<Age>>, world: &mut PreparedWorld, e: &E) {
+    fn sys(_ages: &ReadComponent<Age>, world: &mut PreparedWorld, e: &E) {
         assert_eq!(*world.get_component::<Age>(e.0).unwrap(), Age(10));
     }
 
@@ -28,3 +29,22 @@ fn basic() {
 
     scheduler.execute(&mut world);
 }
+
+#[test]
+fn write() {
+    #[system]
+    fn sys(_ages: &WriteComponent<Age>, world: &mut PreparedWorld, e: &E) {
+        world.get_component_mut::<Age>(e.0).unwrap().0 += 1;
+    }
+
+    let mut world = World::new();
+    let entity = world.insert((), [(Age(10), 2)].iter().copied())[0];
+
+    let mut resources = Resources::new();
+    resources.insert(E(entity));
+
+    let mut scheduler = SchedulerBuilder::new().with(sys).build(resources);
+
+    scheduler.execute(&mut world);
+    assert_eq!(*world.get_component::<Age>(entity).unwrap(), Age(11));
+}
@@ -0,0 +1,91 @@
+//! Testing of multi-entity component access through `PreparedWorld`.
+
+#[macro_use]
+extern crate tonks;
+
+use legion::entity::Entity;
+use legion::world::World;
+use tonks::{PreparedWorld, ReadComponent, Resources, SchedulerBuilder, WriteComponent};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Health(u32);
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Parent(Entity);
+#[derive(Resource)]
+struct Entities(Vec<Entity>);
+
+#[test]
+fn get_many_mut() {
+    #[system]
+    fn sys(_health: &WriteComponent<Health>, world: &mut PreparedWorld, entities: &Entities) {
+        let mut healths = world.get_many_mut::<Health>(&entities.0);
+        let (first, second) = healths.split_at_mut(1);
+        std::mem::swap::<Health>(first[0].as_mut().unwrap(), second[0].as_mut().unwrap());
+        assert!(healths[2].is_none());
+    }
+
+    let mut world = World::new();
+    let mut entities = world
+        .insert((), [(Health(1),), (Health(2),)].iter().copied())
+        .to_vec();
+    entities.push(world.insert((), [(1u8,)].iter().copied())[0]);
+
+    let mut resources = Resources::new();
+    resources.insert(Entities(entities.clone()));
+
+    let mut scheduler = SchedulerBuilder::new().with(sys).build(resources);
+    scheduler.execute(&mut world);
+
+    assert_eq!(
+        *world.get_component::<Health>(entities[0]).unwrap(),
+        Health(2)
+    );
+    assert_eq!(
+        *world.get_component::<Health>(entities[1]).unwrap(),
+        Health(1)
+    );
+}
+
+#[test]
+fn follow() {
+    #[system]
+    fn sys(
+        _parents: &ReadComponent<Parent>,
+        _health: &WriteComponent<Health>,
+        world: &mut PreparedWorld,
+        entities: &Entities,
+    ) {
+        let child = entities.0[1];
+        assert_eq!(
+            *world
+                .follow::<Parent, Health>(child, |parent| parent.0)
+                .unwrap(),
+            Health(10)
+        );
+
+        {
+            let (parent, mut health) = world
+                .follow_mut::<Parent, Health>(child, |parent| parent.0)
+                .unwrap();
+            assert_eq!(parent.0, entities.0[0]);
+            health.0 += 1;
+        }
+
+        assert!(world
+            .follow::<Parent, Health>(entities.0[0], |parent| parent.0)
+            .is_none());
+    }
+
+    let mut world = World::new();
+    let parent = world.insert((), [(Health(10),)].iter().copied())[0];
+    let child = world.insert((), [(Health(1), Parent(parent))].iter().copied())[0];
+
+    let mut resources = Resources::new();
+    resources.insert(Entities(vec![parent, child]));
+
+    let mut scheduler = SchedulerBuilder::new().with(sys).build(resources);
+    scheduler.execute(&mut world);
+
+    assert_eq!(*world.get_component::<Health>(parent).unwrap(), Health(11));
+    assert_eq!(*world.get_component::<Health>(child).unwrap(), Health(1));
+}
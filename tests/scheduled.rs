@@ -0,0 +1,144 @@
+//! Testing of events scheduled for later dispatches.
+
+use legion::world::World;
+use std::time::Duration;
+use tonks::{EventHandler, EventsBuilder, Resources, System, SystemData, Trigger, Write};
+
+struct Fire(u32);
+
+#[derive(Default)]
+struct Log(Vec<u32>);
+
+/// Schedules events on the first run only.
+struct Sys {
+    first_run: bool,
+}
+
+impl System for Sys {
+    type SystemData = Trigger<Fire>;
+
+    fn run(&mut self, trigger: <Self::SystemData as SystemData>::Output) {
+        if !self.first_run {
+            return;
+        }
+        self.first_run = false;
+
+        assert_eq!(trigger.current_tick(), 0);
+        trigger.trigger_after(Fire(2), 2);
+        trigger.trigger_at(Fire(1), 1);
+        trigger.trigger_after(Fire(0), 0);
+    }
+}
+
+struct Handler;
+
+impl EventHandler<Fire> for Handler {
+    type HandlerData = Write<Log>;
+
+    fn handle(&mut self, event: &Fire, log: &mut <Self::HandlerData as SystemData>::Output) {
+        log.0.push(event.0);
+    }
+}
+
+#[test]
+fn handled_on_target_tick() {
+    let mut scheduler = EventsBuilder::new()
+        .with(Handler)
+        .finish()
+        .with(Sys { first_run: true })
+        .build(Resources::new());
+
+    let mut world = World::new();
+
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Log>().0, vec![0]);
+    assert_eq!(scheduler.tick(), 1);
+
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Log>().0, vec![0, 1]);
+
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Log>().0, vec![0, 1, 2]);
+
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Log>().0, vec![0, 1, 2]);
+}
+
+#[test]
+fn scheduler_api() {
+    let mut scheduler = EventsBuilder::new()
+        .with(Handler)
+        .finish()
+        .build(Resources::new());
+
+    let mut world = World::new();
+
+    scheduler.trigger_at(Fire(3), 2);
+    scheduler.trigger_after(Fire(2), 1);
+    scheduler.trigger_after(Fire(1), 1);
+    scheduler.trigger_in(Fire(0), Duration::from_secs(0));
+    // Never handled, but dropped with the scheduler.
+    scheduler.trigger_in(Fire(4), Duration::from_secs(3600));
+
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Log>().0, vec![0]);
+
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Log>().0, vec![0, 2, 1]);
+
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Log>().0, vec![0, 2, 1, 3]);
+}
+
+#[derive(Default)]
+struct BatchSizes(Vec<usize>);
+
+/// Schedules several events for the next tick on the first run only.
+struct BatchSys {
+    first_run: bool,
+}
+
+impl System for BatchSys {
+    type SystemData = Trigger<Fire>;
+
+    fn run(&mut self, trigger: <Self::SystemData as SystemData>::Output) {
+        if self.first_run {
+            self.first_run = false;
+            for x in 0..3 {
+                trigger.trigger_after(Fire(x), 1);
+            }
+            trigger.trigger_after(Fire(3), 2);
+        }
+    }
+}
+
+struct BatchHandler;
+
+impl EventHandler<Fire> for BatchHandler {
+    type HandlerData = Write<BatchSizes>;
+
+    fn handle(&mut self, _event: &Fire, _sizes: &mut <Self::HandlerData as SystemData>::Output) {
+        unreachable!()
+    }
+
+    fn handle_batch(&mut self, events: &[Fire], sizes: <Self::HandlerData as SystemData>::Output) {
+        sizes.0.push(events.len());
+    }
+}
+
+#[test]
+fn batched_per_tick() {
+    let mut scheduler = EventsBuilder::new()
+        .with(BatchHandler)
+        .finish()
+        .with(BatchSys { first_run: true })
+        .build(Resources::new());
+
+    let mut world = World::new();
+    for _ in 0..3 {
+        scheduler.execute(&mut world);
+    }
+
+    // Events due at the same tick are handled as one batch.
+    assert_eq!(scheduler.resources().get::<BatchSizes>().0, vec![3, 1]);
+}
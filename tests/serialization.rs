@@ -0,0 +1,92 @@
+#![cfg(feature = "serde")]
+
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use tonks::{ResourceRegistry, Resources};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Score(u64);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Settings {
+    name: String,
+    volume: f32,
+}
+
+/// Not registered, so it should be skipped.
+struct Unregistered;
+
+fn registry() -> ResourceRegistry {
+    ResourceRegistry::new()
+        .with::<Score>("score")
+        .with::<Settings>("settings")
+}
+
+fn resources() -> Resources {
+    let mut resources = Resources::new();
+    resources.insert(Score(1_000));
+    resources.insert(Settings {
+        name: String::from("player"),
+        volume: 0.5,
+    });
+    resources.insert(Unregistered);
+    resources
+}
+
+#[test]
+fn json_round_trip() {
+    let registry = registry();
+
+    let json = serde_json::to_string(&registry.serialize(&resources())).unwrap();
+    assert_eq!(
+        json,
+        r#"{"score":1000,"settings":{"name":"player","volume":0.5}}"#
+    );
+
+    let mut loaded = Resources::new();
+    registry
+        .deserialize_into(&mut serde_json::Deserializer::from_str(&json), &mut loaded)
+        .unwrap();
+
+    assert_eq!(loaded.get::<Score>(), &Score(1_000));
+    assert_eq!(loaded.get::<Settings>().name, "player");
+    assert!(loaded.try_get::<Unregistered>().is_none());
+}
+
+#[test]
+fn bincode_round_trip() {
+    let registry = registry();
+    let options = bincode::DefaultOptions::new();
+
+    let bytes = options
+        .serialize(&registry.serialize(&resources()))
+        .unwrap();
+
+    let mut loaded = Resources::new();
+    loaded.insert(Score(0));
+    registry
+        .deserialize_into(
+            &mut bincode::Deserializer::from_slice(&bytes, options),
+            &mut loaded,
+        )
+        .unwrap();
+
+    assert_eq!(loaded.get::<Score>(), &Score(1_000));
+    assert_eq!(
+        loaded.get::<Settings>(),
+        &Settings {
+            name: String::from("player"),
+            volume: 0.5,
+        }
+    );
+}
+
+#[test]
+fn unknown_resource() {
+    let mut resources = Resources::new();
+    let result = registry().deserialize_into(
+        &mut serde_json::Deserializer::from_str(r#"{"health":10}"#),
+        &mut resources,
+    );
+    assert!(result.is_err());
+}
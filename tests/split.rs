@@ -0,0 +1,115 @@
+//! Testing of split borrows of the world.
+
+#[macro_use]
+extern crate tonks;
+
+use legion::query::{Read, Write};
+use legion::world::World;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tonks::{PreparedWorld, Query, Resources, SchedulerBuilder, Split, With, Without};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Position(f32);
+
+#[derive(Clone, Copy)]
+struct Velocity(f32);
+
+#[derive(Clone, Copy)]
+struct Player;
+
+#[test]
+fn nested_iteration() {
+    #[system]
+    fn sys(
+        queries: &mut Split<(Query<Write<Position>>, Query<Read<Velocity>>)>,
+        world: &mut PreparedWorld,
+    ) {
+        let (mut positions, mut velocities) = world.split(queries);
+
+        for mut position in positions.iter() {
+            for velocity in velocities.iter() {
+                position.0 += velocity.0;
+            }
+        }
+
+        let count = AtomicUsize::new(0);
+        positions.par_for_each(|_| {
+            count.fetch_add(1, Ordering::Relaxed);
+        });
+        assert_eq!(count.into_inner(), 2);
+    }
+
+    let mut world = World::new();
+    let entities = world.insert(
+        (),
+        [
+            (Position(0.0), Velocity(1.0)),
+            (Position(1.0), Velocity(2.0)),
+        ]
+        .iter()
+        .copied(),
+    );
+
+    let mut scheduler = SchedulerBuilder::new().with(sys).build(Resources::new());
+    scheduler.execute(&mut world);
+
+    assert_eq!(
+        *world.get_component::<Position>(entities[0]).unwrap(),
+        Position(3.0)
+    );
+    assert_eq!(
+        *world.get_component::<Position>(entities[1]).unwrap(),
+        Position(4.0)
+    );
+}
+
+#[test]
+fn disjoint_filters() {
+    #[system]
+    fn sys(
+        queries: &mut Split<(
+            Query<Write<Position>, With<Player>>,
+            Query<Write<Position>, Without<Player>>,
+        )>,
+        world: &mut PreparedWorld,
+    ) {
+        let (mut players, mut others) = world.split(queries);
+
+        for mut player in players.iter() {
+            for mut other in others.iter() {
+                player.0 += 1.0;
+                other.0 -= 1.0;
+            }
+        }
+    }
+
+    let mut world = World::new();
+    let player = world.insert((), [(Position(0.0), Player)].iter().copied())[0];
+    let other = world.insert((), [(Position(0.0),)].iter().copied())[0];
+
+    let mut scheduler = SchedulerBuilder::new().with(sys).build(Resources::new());
+    scheduler.execute(&mut world);
+
+    assert_eq!(
+        *world.get_component::<Position>(player).unwrap(),
+        Position(1.0)
+    );
+    assert_eq!(
+        *world.get_component::<Position>(other).unwrap(),
+        Position(-1.0)
+    );
+}
+
+#[test]
+#[should_panic(expected = "access overlapping components")]
+fn overlapping_queries_fail_to_build() {
+    #[system]
+    fn sys(
+        queries: &mut Split<(Query<Write<Position>>, Query<Read<Position>>)>,
+        world: &mut PreparedWorld,
+    ) {
+        let _ = world.split(queries);
+    }
+
+    SchedulerBuilder::new().with(sys).build(Resources::new());
+}
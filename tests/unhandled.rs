@@ -0,0 +1,96 @@
+//! Testing of policies for events without handlers.
+
+use legion::world::World;
+use std::panic::{self, AssertUnwindSafe};
+use tonks::{
+    EventHandler, EventsBuilder, Resources, System, SystemData, Trigger, UnhandledEventPolicy,
+    UnhandledEvents,
+};
+
+struct Handled;
+struct Typo;
+
+struct Sys;
+
+impl System for Sys {
+    type SystemData = (Trigger<Handled>, Trigger<Typo>);
+
+    fn run(&mut self, (handled, typo): <Self::SystemData as SystemData>::Output) {
+        handled.trigger(Handled);
+        typo.trigger_batched(vec![Typo, Typo]);
+    }
+}
+
+struct Handler;
+
+impl EventHandler<Handled> for Handler {
+    type HandlerData = ();
+
+    fn handle(&mut self, _event: &Handled, _data: &mut <Self::HandlerData as SystemData>::Output) {}
+}
+
+#[test]
+fn ignore() {
+    let mut scheduler = EventsBuilder::new()
+        .with(Handler)
+        .finish()
+        .with(Sys)
+        .build(Resources::new());
+
+    scheduler.execute(&mut World::new());
+
+    assert!(scheduler.resources().try_get::<UnhandledEvents>().is_none());
+}
+
+#[test]
+fn collect() {
+    let mut scheduler = EventsBuilder::new()
+        .with(Handler)
+        .with_unhandled_event_policy(UnhandledEventPolicy::Collect)
+        .finish()
+        .with(Sys)
+        .build(Resources::new());
+
+    scheduler.execute(&mut World::new());
+    scheduler.trigger(Typo);
+    scheduler.execute(&mut World::new());
+
+    let unhandled = scheduler.resources().get::<UnhandledEvents>();
+    assert_eq!(unhandled.count::<Typo>(), 5);
+    assert_eq!(unhandled.count::<Handled>(), 0);
+    assert_eq!(
+        unhandled.iter().collect::<Vec<_>>(),
+        vec![("unhandled::Typo", 5)]
+    );
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "event of type unhandled::Typo was triggered")]
+fn panic() {
+    let mut scheduler = EventsBuilder::new()
+        .with(Handler)
+        .with_unhandled_event_policy(UnhandledEventPolicy::Panic)
+        .finish()
+        .with(Sys)
+        .build(Resources::new());
+
+    scheduler.execute(&mut World::new());
+}
+
+#[test]
+#[cfg(debug_assertions)]
+fn panic_after_dispatch() {
+    let mut scheduler = EventsBuilder::new()
+        .with(Handler)
+        .with_unhandled_event_policy(UnhandledEventPolicy::Panic)
+        .finish()
+        .with(Sys)
+        .build(Resources::new());
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| scheduler.execute(&mut World::new())));
+    assert!(result.is_err());
+
+    // The dispatch completed before the panic.
+    assert_eq!(scheduler.tick(), 1);
+}